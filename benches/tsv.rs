@@ -0,0 +1,105 @@
+//! Parses a synthetic NYSE-sized symbol list, comparing the columnar
+//! `Tsv` with the `HashMap` per row it replaced: how long it takes,
+//! and how many allocations it makes.
+//!
+//! Run with `cargo bench --bench tsv`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::HashMap,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use nyse_logos::Tsv;
+
+/// Counts allocations, so that each representation's can be told
+/// apart.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const ROWS: usize = 3_000;
+const ITERATIONS: u32 = 20;
+
+/// A tab-separated list of `ROWS` rows of 8 columns.
+fn synthetic_list() -> String {
+    let mut list = String::from(
+        "Symbol\tCompany\tUnit Of Trade\tCUSIP\tRound Lot\tIssue Type\tListing Venue\tStatus\n",
+    );
+    for i in 0..ROWS {
+        list.push_str(&format!(
+            "S{i:04}\tCompany Number {i}, Inc.\t100\t{i:09}\t100\tCommon Stock\tNYSE\tActive\n"
+        ));
+    }
+    list
+}
+
+/// The rows as a `HashMap` from header to value each, as they were
+/// stored before `Tsv` became columnar.
+fn hash_map_rows(list: &str) -> Vec<HashMap<String, String>> {
+    let mut lines = list.lines();
+    let headers = lines
+        .next()
+        .unwrap_or_default()
+        .split('\t')
+        .collect::<Vec<_>>();
+
+    lines
+        .map(|line| {
+            line.split('\t')
+                .enumerate()
+                .map(|(i, value)| (headers[i].to_string(), value.to_string()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Runs `parse` `ITERATIONS` times, returning the average time and
+/// allocations per run.
+fn measure<T>(parse: impl Fn() -> T) -> (Duration, usize) {
+    black_box(parse());
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(parse());
+    }
+    let elapsed = started.elapsed() / ITERATIONS;
+
+    (
+        elapsed,
+        (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / ITERATIONS as usize,
+    )
+}
+
+fn main() {
+    let list = synthetic_list();
+
+    let (columnar_time, columnar_allocations) =
+        measure(|| Tsv::from_delimited(&list, '\t').expect("synthetic list parses"));
+    let (hash_map_time, hash_map_allocations) = measure(|| hash_map_rows(&list));
+
+    println!("parsing {ROWS} rows of 8 columns (average of {ITERATIONS} runs):");
+    println!("  columnar Tsv:     {columnar_time:>10.2?}, {columnar_allocations:>7} allocations");
+    println!("  HashMap per row:  {hash_map_time:>10.2?}, {hash_map_allocations:>7} allocations");
+    println!(
+        "  {:.0}% fewer allocations",
+        100.0 * (1.0 - columnar_allocations as f64 / hash_map_allocations as f64)
+    );
+}
@@ -0,0 +1,301 @@
+//! Archive (`.tar.gz` / `.zip`) output.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use log::trace;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::{JoinError, JoinHandle},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter};
+
+use crate::{atomic, Error, Result};
+
+/// The kind of archive, chosen by its file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// `.tar.gz` or `.tgz`
+    TarGz,
+    /// `.zip`
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// The format for the given archive path, or `None` if its
+    /// extension isn't a supported one.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// A channel to an archive's writer task (see
+/// [`Archive::spawn_writer`]), taking `(name, content)` entries.
+pub type EntrySender = mpsc::Sender<(String, Vec<u8>)>;
+
+enum Writer {
+    TarGz(Box<tar::Builder<GzEncoder<BufWriter<File>>>>),
+    Zip(Box<ZipWriter<BufWriter<File>>>),
+}
+
+/// An archive being written. Entries are stored flat (under their
+/// file name only).
+///
+/// With `reproducible`, entries get a fixed modification time (the
+/// Unix epoch for tar, 1980-01-01 for zip), so that the same files
+/// always produce the same archive.
+pub struct Archive {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    writer: Writer,
+    reproducible: bool,
+}
+
+impl Archive {
+    /// Starts writing the archive at `path`, in the format given by
+    /// its extension. The archive is written to a temporary file and
+    /// only replaces `path` once it's [finished](Self::finish).
+    pub fn create(path: &Path, reproducible: bool) -> Result<Self> {
+        let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+            Error::Format(format!(
+                "unsupported archive '{}' (expected a .tar.gz, .tgz or .zip path)",
+                path.display()
+            ))
+        })?;
+
+        let tmp_path = atomic::tmp_path(path);
+        let file = File::create(&tmp_path).map_err(|source| Error::Io {
+            path: tmp_path.clone(),
+            source,
+        })?;
+        let file = BufWriter::new(file);
+
+        let writer = match format {
+            ArchiveFormat::TarGz => Writer::TarGz(Box::new(tar::Builder::new(GzEncoder::new(
+                file,
+                Compression::default(),
+            )))),
+            ArchiveFormat::Zip => Writer::Zip(Box::new(ZipWriter::new(file))),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            tmp_path,
+            writer,
+            reproducible,
+        })
+    }
+
+    /// Adds a file to the archive.
+    pub fn append(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        trace!("archiving '{name}' ({} bytes)", content.len());
+
+        let result = match &mut self.writer {
+            Writer::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(if self.reproducible {
+                    0
+                } else {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs())
+                });
+                builder.append_data(&mut header, name, content)
+            }
+            Writer::Zip(zip) => {
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .unix_permissions(0o644)
+                    .last_modified_time(if self.reproducible {
+                        DateTime::default()
+                    } else {
+                        DateTime::default_for_write()
+                    });
+                zip.start_file(name, options)
+                    .map_err(io::Error::from)
+                    .and_then(|()| zip.write_all(content))
+            }
+        };
+
+        result.map_err(|source| self.io_error(source))
+    }
+
+    /// Finishes writing the archive and moves it into place.
+    pub fn finish(self) -> Result<()> {
+        let result = match self.writer {
+            Writer::TarGz(builder) => builder
+                .into_inner()
+                .and_then(GzEncoder::finish)
+                .and_then(|mut file| file.flush()),
+            Writer::Zip(zip) => zip
+                .finish()
+                .map_err(io::Error::from)
+                .and_then(|mut file| file.flush()),
+        };
+
+        let result = result.and_then(|()| atomic::rename(&self.tmp_path, &self.path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&self.tmp_path);
+        }
+
+        result.map_err(|source| Error::Io {
+            path: self.path,
+            source,
+        })
+    }
+
+    /// Abandons the archive, leaving whatever was at its path as is.
+    fn discard(self) {
+        drop(self.writer);
+        let _ = std::fs::remove_file(&self.tmp_path);
+        trace!("discarded unfinished archive '{}'", self.path.display());
+    }
+
+    /// Adds the given `(path, name)` files, in name order.
+    pub fn append_files(&mut self, mut files: Vec<(PathBuf, String)>) -> Result<()> {
+        files.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+        for (path, name) in files {
+            let content = std::fs::read(&path).map_err(|source| Error::Io { path, source })?;
+            self.append(&name, &content)?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the archive to a dedicated writer task, returning a
+    /// channel to send `(name, content)` entries to and the task. Once
+    /// every sender has been dropped, the archive is finished if
+    /// [`WriterTask::finish`] was called, and otherwise (e.g. if the
+    /// run stopped early) discarded, so that an incomplete archive
+    /// never replaces a previous one.
+    ///
+    /// Reproducible archives are written in name order, so their
+    /// entries are held in memory until the channel closes.
+    pub fn spawn_writer(mut self) -> (EntrySender, WriterTask) {
+        let (tx, mut rx) = mpsc::channel::<(String, Vec<u8>)>(64);
+        let (commit, committed) = oneshot::channel();
+
+        let handle = tokio::task::spawn_blocking(move || {
+            if self.reproducible {
+                let mut entries = BTreeMap::new();
+                while let Some((name, content)) = rx.blocking_recv() {
+                    entries.insert(name, content);
+                }
+                for (name, content) in entries {
+                    self.append(&name, &content)?;
+                }
+            } else {
+                while let Some((name, content)) = rx.blocking_recv() {
+                    self.append(&name, &content)?;
+                }
+            }
+
+            if committed.blocking_recv().is_ok() {
+                self.finish()
+            } else {
+                self.discard();
+                Ok(())
+            }
+        });
+
+        (tx, WriterTask { commit, handle })
+    }
+
+    fn io_error(&self, source: io::Error) -> Error {
+        Error::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+/// An archive's writer task (see [`Archive::spawn_writer`]). Dropping
+/// it rather than [finishing](Self::finish) it discards the archive.
+pub struct WriterTask {
+    commit: oneshot::Sender<()>,
+    handle: JoinHandle<Result<()>>,
+}
+
+impl WriterTask {
+    /// Finishes the archive and moves it into place, once every
+    /// entry sender has been dropped.
+    pub async fn finish(self) -> std::result::Result<Result<()>, JoinError> {
+        let _ = self.commit.send(());
+        self.handle.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path for an archive in a fresh temporary directory.
+    fn archive_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "nyse-logos-archive-test-{}-{name}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("logos.zip")
+    }
+
+    #[tokio::test]
+    async fn finished_archives_replace_the_previous_one() {
+        let path = archive_path("finished");
+        std::fs::write(&path, "previous").unwrap();
+
+        let (tx, writer) = Archive::create(&path, true).unwrap().spawn_writer();
+        tx.send(("AAPL.svg".to_string(), b"<svg/>".to_vec()))
+            .await
+            .unwrap();
+        drop(tx);
+        writer.finish().await.unwrap().unwrap();
+
+        let archive = std::fs::read(&path).unwrap();
+        assert!(archive.starts_with(b"PK"));
+        assert!(!atomic::tmp_path(&path).exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unfinished_archives_are_discarded() {
+        let path = archive_path("unfinished");
+        std::fs::write(&path, "previous").unwrap();
+
+        let (tx, writer) = Archive::create(&path, false).unwrap().spawn_writer();
+        tx.send(("AAPL.svg".to_string(), b"<svg/>".to_vec()))
+            .await
+            .unwrap();
+        drop(writer);
+        drop(tx);
+
+        // the writer task cleans up in the background
+        for _ in 0..100 {
+            if !atomic::tmp_path(&path).exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(!atomic::tmp_path(&path).exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous");
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+//! Atomic file writes.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Writes `contents` to `path` atomically, by writing a temporary
+/// file next to it and renaming it into place, so that readers
+/// never observe a partially-written file.
+pub async fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp_path = tmp_path(path);
+
+    if let Err(e) = tokio::fs::write(&tmp_path, contents).await {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(e);
+    }
+
+    commit(&tmp_path, path).await
+}
+
+/// Moves a finished temporary file (see [`tmp_path`]) into place at
+/// `path`, removing it if that fails.
+pub async fn commit(tmp_path: &Path, path: &Path) -> io::Result<()> {
+    let (from, to) = (tmp_path.to_path_buf(), path.to_path_buf());
+    let renamed = tokio::task::spawn_blocking(move || rename(&from, &to))
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)));
+
+    if renamed.is_err() {
+        let _ = tokio::fs::remove_file(tmp_path).await;
+    }
+
+    renamed
+}
+
+/// The temporary path used while writing `path` (a hidden sibling
+/// file, so that the rename stays on the same filesystem).
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Renames `from` over `to`, replacing it if it exists.
+///
+/// On Windows, replacing a file fails while another process (e.g. a
+/// virus scanner or indexer) has it open, so the rename is retried a
+/// few times before giving up.
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        const ATTEMPTS: u64 = 5;
+
+        for attempt in 1..ATTEMPTS {
+            match std::fs::rename(from, to) {
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                    std::thread::sleep(std::time::Duration::from_millis(20 * attempt));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    std::fs::rename(from, to)
+}
+
+/// Removes temporary files left in `dir` by interrupted writes,
+/// returning their paths.
+pub async fn remove_stale(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(removed),
+        Err(e) => return Err(e),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') && name.ends_with(".tmp") && entry.file_type().await?.is_file() {
+            tokio::fs::remove_file(entry.path()).await?;
+            removed.push(entry.path());
+        }
+    }
+
+    removed.sort();
+
+    Ok(removed)
+}
@@ -0,0 +1,727 @@
+//! Fetching many symbols' logos at once: concurrently, retrying
+//! transient failures, and pausing while the logo CDN keeps failing.
+
+use std::{
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_util::{Stream, StreamExt};
+use log::{trace, warn};
+use regex::Regex;
+use tokio::task::JoinSet;
+
+use crate::{
+    archive::EntrySender,
+    breaker::CircuitBreaker,
+    compress,
+    concurrency::Concurrency,
+    events::{EventSender, FetchEvent},
+    exit::Status,
+    failure::FailureKind,
+    layout,
+    results::{FetchRecord, Outcome, Summary},
+    retry::{self, RetryPolicy},
+    shutdown::Shutdown,
+    test_symbols, ticker, Error, LogoConfig, LogoOutcome, Result, Symbol, Validators,
+};
+
+/// Which symbols' logos to fetch.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolFilter {
+    /// Only symbols matching at least one of these (matched against
+    /// the uppercase ticker), unless it's empty.
+    pub include: Vec<Regex>,
+    /// Symbols matching any of these are skipped, even if they match
+    /// `include`.
+    pub exclude: Vec<Regex>,
+    /// Whether [test symbols](test_symbols) are kept.
+    pub include_test_symbols: bool,
+}
+
+impl SymbolFilter {
+    /// Whether the given symbol passes the filter, logging it if not.
+    pub fn keeps(&self, symbol: &str) -> bool {
+        let symbol = symbol.trim().to_uppercase();
+
+        if !self.include_test_symbols && test_symbols::is_test_symbol(&symbol) {
+            trace!("skipping test symbol '{symbol}'");
+            return false;
+        }
+
+        if self.exclude.iter().any(|re| re.is_match(&symbol)) {
+            trace!("skipping excluded symbol '{symbol}'");
+            return false;
+        }
+
+        if !self.include.is_empty() && !self.include.iter().any(|re| re.is_match(&symbol)) {
+            trace!("skipping symbol '{symbol}' not matching any include filter");
+            return false;
+        }
+
+        true
+    }
+}
+
+/// How [`fetch_logos`] (or a [`Batch`]) fetches logos.
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// The directory logos are written to (created if need be).
+    pub output: PathBuf,
+    /// Whether logos already in `output` are fetched again, rather
+    /// than skipped.
+    pub force: bool,
+    /// How many logos are fetched at once (at least 1).
+    pub jobs: usize,
+    /// Whether `jobs` is lowered while the logo CDN rate limits
+    /// requests (see [`Concurrency::adaptive`]).
+    pub adaptive_jobs: bool,
+    /// Which symbols' logos are fetched.
+    pub filter: SymbolFilter,
+    /// The gzip level (0-9) logos are compressed with (and written as
+    /// `.svgz`), if at all.
+    pub compress: Option<u32>,
+    /// Whether logos are written into [shards](layout::shard).
+    pub sharded: bool,
+    /// How each logo is requested.
+    pub logo: LogoConfig,
+    /// How failed requests are retried.
+    pub retry: RetryPolicy,
+    /// How many fetches in a row may fail before the rest are paused
+    /// (or 0 to never pause), how long for, and how many pauses in a
+    /// row it takes to give up (see [`CircuitBreaker::new`]).
+    pub breaker_threshold: u32,
+    pub breaker_cool_down: Duration,
+    pub breaker_max_trips: u32,
+    /// Where [progress events](FetchEvent) are sent, if anywhere.
+    pub events: Option<EventSender>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            output: PathBuf::from("."),
+            force: false,
+            jobs: 8,
+            adaptive_jobs: false,
+            filter: SymbolFilter::default(),
+            compress: None,
+            sharded: false,
+            logo: LogoConfig::default(),
+            retry: RetryPolicy::default(),
+            breaker_threshold: 20,
+            breaker_cool_down: Duration::from_secs(30),
+            breaker_max_trips: 3,
+            events: None,
+        }
+    }
+}
+
+impl FetchOptions {
+    /// The concurrency limit and circuit breaker fetches are made
+    /// under.
+    pub(crate) fn limits(&self) -> (Concurrency, CircuitBreaker) {
+        let concurrency = if self.adaptive_jobs {
+            Concurrency::adaptive(self.jobs)
+        } else {
+            Concurrency::fixed(self.jobs)
+        };
+        let breaker = CircuitBreaker::new(
+            self.breaker_threshold,
+            self.breaker_cool_down,
+            self.breaker_max_trips,
+        );
+        (concurrency, breaker)
+    }
+
+    /// The extension logos are written with.
+    pub fn extension(&self) -> &'static str {
+        if self.compress.is_some() {
+            "svgz"
+        } else {
+            "svg"
+        }
+    }
+}
+
+/// What [`fetch_logos`] did.
+#[derive(Debug, Clone, Default)]
+pub struct FetchSummary {
+    /// A record per symbol that passed the filter, sorted by symbol.
+    pub records: Vec<FetchRecord>,
+    /// How many symbols had each outcome.
+    pub totals: Summary,
+    /// Whether fetching was given up on because the logo CDN kept
+    /// failing (leaving the rest [not attempted](Outcome::NotAttempted)).
+    pub gave_up: bool,
+}
+
+impl FetchSummary {
+    /// What the `nyse-logos` binary would exit with after these
+    /// fetches (see [`Status::of_fetches`]).
+    pub fn status(&self, fail_on_missing: bool, threshold: usize) -> Status {
+        Status::of_fetches(&self.totals, self.gave_up, fail_on_missing, threshold)
+    }
+}
+
+/// Fetches the given symbols' logos into the `options`' output
+/// directory, skipping those filtered out and (unless forced) those
+/// already there.
+pub async fn fetch_logos(
+    client: &reqwest::Client,
+    symbols: &[Symbol],
+    options: &FetchOptions,
+) -> Result<FetchSummary> {
+    fetch_with(Batch::new(client.clone(), options), symbols, options).await
+}
+
+/// Starts fetching the given symbols' logos into the `options'`
+/// output directory, yielding each one's result as soon as it's done
+/// (so in the order they finish, not the order given). Symbols that
+/// are filtered out, unsupported or (unless forced) already have a
+/// logo aren't fetched, so aren't yielded.
+///
+/// The fetches run in tasks of their own, so this must be called
+/// within a Tokio runtime. Dropping the stream cancels any that
+/// haven't finished (leaving temporary files behind, for
+/// [`atomic::remove_stale`](crate::atomic::remove_stale) to clean
+/// up).
+pub fn fetch_logo_stream(
+    client: &reqwest::Client,
+    symbols: &[Symbol],
+    options: &FetchOptions,
+) -> impl Stream<Item = FetchResult> + Send + Unpin + 'static {
+    let mut batch = Batch::new(client.clone(), options);
+    start(&mut batch, symbols, options);
+    batch
+}
+
+/// [`fetch_logos`], with the fetches made by `batch`.
+pub(crate) async fn fetch_with(
+    mut batch: Batch,
+    symbols: &[Symbol],
+    options: &FetchOptions,
+) -> Result<FetchSummary> {
+    create_dir(&options.output).await?;
+
+    let mut records = start(&mut batch, symbols, options);
+    records.extend(
+        batch
+            .by_ref()
+            .map(|result| result.record())
+            .collect::<Vec<_>>()
+            .await,
+    );
+    records.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    let mut totals = Summary::default();
+    for record in &records {
+        totals.record(record);
+    }
+    if let Some(events) = &options.events {
+        events.send(FetchEvent::Finished {
+            summary: totals.clone(),
+        });
+    }
+
+    Ok(FetchSummary {
+        records,
+        totals,
+        gave_up: batch.gave_up(),
+    })
+}
+
+/// Spawns the fetches of those of the given symbols' logos that need
+/// fetching into `batch`, returning the records of those that don't
+/// (bar the ones filtered out).
+pub(crate) fn start(
+    batch: &mut Batch,
+    symbols: &[Symbol],
+    options: &FetchOptions,
+) -> Vec<FetchRecord> {
+    let kept = symbols
+        .iter()
+        .map(|symbol| symbol.ticker.trim())
+        .filter(|listed| options.filter.keeps(listed))
+        .collect::<Vec<_>>();
+    if let Some(events) = &options.events {
+        events.send(FetchEvent::Started { total: kept.len() });
+    }
+
+    let mut skipped = Vec::new();
+    for listed in kept {
+        match plan(listed, options) {
+            Plan::Fetch(job) => batch.spawn(job),
+            Plan::Skip(record) => {
+                if let Some(events) = &options.events {
+                    events.send(FetchEvent::finished(&record));
+                }
+                skipped.push(record);
+            }
+        }
+    }
+    skipped
+}
+
+/// What's to be done about a symbol's logo.
+pub(crate) enum Plan {
+    Fetch(LogoJob),
+    /// Nothing, because the symbol is unsupported or (unless forced)
+    /// its logo is already there.
+    Skip(FetchRecord),
+}
+
+/// What's to be done about the logo of `listed` (a ticker, as listed).
+pub(crate) fn plan(listed: &str, options: &FetchOptions) -> Plan {
+    let Some(file_stem) = ticker::file_stem_cased(listed, options.logo.file_case) else {
+        warn!("skipping unsupported symbol '{listed}'");
+        return Plan::Skip(FetchRecord::skipped(listed, Outcome::SkippedInvalidSymbol));
+    };
+
+    let existing = layout::existing(
+        &options.output,
+        &file_stem,
+        options.extension(),
+        options.sharded,
+    );
+    if let Some(existing) = existing.filter(|_| !options.force) {
+        trace!("skipping existing logo for '{listed}'");
+        let mut record = FetchRecord::skipped(listed, Outcome::SkippedExisting);
+        record.path = Some(existing);
+        return Plan::Skip(record);
+    }
+
+    let logo_file = layout::logo_file(&file_stem, options.extension(), options.sharded);
+    let dest = match options.output.join(logo_file).parent() {
+        Some(dest) => dest.to_path_buf(),
+        None => options.output.clone(),
+    };
+
+    Plan::Fetch(LogoJob {
+        symbol: listed.to_string(),
+        target: Target::Dir(dest),
+        validators: None,
+    })
+}
+
+pub(crate) async fn create_dir(path: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(path)
+        .await
+        .map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Where a [`Batch`] puts a logo.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// Written to a directory (see [`fetch_logo`](crate::fetch_logo)),
+    /// which is created first if need be.
+    Dir(PathBuf),
+    /// Sent to an [archive writer](crate::archive::Archive::spawn_writer)
+    /// rather than written to disk; the outcome's path is the logo's
+    /// name within the archive.
+    Archive(EntrySender),
+    /// Nowhere: the logo is only [checked](crate::check_logo) for.
+    Check,
+}
+
+/// A logo for a [`Batch`] to fetch.
+#[derive(Debug, Clone)]
+pub struct LogoJob {
+    /// The ticker, as listed (which matters for
+    /// [`FileCase::Original`](ticker::FileCase::Original)).
+    pub symbol: String,
+    pub target: Target,
+    /// Those of the logo already at the target, to make the request
+    /// conditional.
+    pub validators: Option<Validators>,
+}
+
+/// What became of a [`LogoJob`].
+#[derive(Debug)]
+pub struct FetchResult {
+    /// The job's symbol.
+    pub symbol: String,
+    /// What the fetch came to, or `None` if it was never attempted
+    /// (because a shutdown was requested, or the circuit breaker gave
+    /// up).
+    pub fetched: Option<Fetched>,
+}
+
+/// A fetch a [`Batch`] made.
+#[derive(Debug)]
+pub struct Fetched {
+    pub result: Result<LogoOutcome>,
+    /// How many times the logo was requested (including retries).
+    pub attempts: u32,
+    pub duration: Duration,
+}
+
+impl FetchResult {
+    /// The result as a line of the results log.
+    pub fn record(&self) -> FetchRecord {
+        match &self.fetched {
+            Some(fetched) => FetchRecord::from_result(
+                &self.symbol,
+                &fetched.result,
+                fetched.attempts,
+                fetched.duration,
+            ),
+            None => FetchRecord::skipped(&self.symbol, Outcome::NotAttempted),
+        }
+    }
+
+    /// The fetch's outcome, or if it failed (see
+    /// [`FailureKind::classify`]), an [`Error::LogoFetch`] saying why.
+    /// Returns `None` if the fetch was never attempted.
+    pub fn into_result(self) -> Option<Result<LogoOutcome>> {
+        let fetched = self.fetched?;
+        let Some(kind) = FailureKind::classify(&fetched.result) else {
+            return Some(fetched.result);
+        };
+
+        Some(Err(Error::LogoFetch {
+            symbol: self.symbol,
+            kind,
+            source: fetched.result.err().map(Box::new),
+        }))
+    }
+}
+
+/// Logos being fetched, each in a task of its own, under a shared
+/// [concurrency limit](Concurrency), [retry policy](RetryPolicy) and
+/// [circuit breaker](CircuitBreaker).
+pub struct Batch {
+    client: reqwest::Client,
+    config: Arc<LogoConfig>,
+    retry: RetryPolicy,
+    concurrency: Arc<Concurrency>,
+    breaker: Arc<CircuitBreaker>,
+    compress: Option<u32>,
+    shutdown: Option<Shutdown>,
+    events: Option<EventSender>,
+    tasks: JoinSet<FetchResult>,
+}
+
+impl Batch {
+    /// An empty batch, fetching as the `options` say (bar which logos
+    /// to fetch, and where to, which is up to its [jobs](LogoJob)).
+    pub fn new(client: reqwest::Client, options: &FetchOptions) -> Self {
+        let (concurrency, breaker) = options.limits();
+        Self::sharing(
+            client,
+            Arc::new(options.logo.clone()),
+            options,
+            Arc::new(concurrency),
+            Arc::new(breaker),
+        )
+    }
+
+    /// An empty batch whose fetches count against the given
+    /// concurrency limit and circuit breaker (e.g. a
+    /// [`LogoFetcher`](crate::LogoFetcher)'s), along with those of
+    /// any other batch sharing them.
+    pub(crate) fn sharing(
+        client: reqwest::Client,
+        config: Arc<LogoConfig>,
+        options: &FetchOptions,
+        concurrency: Arc<Concurrency>,
+        breaker: Arc<CircuitBreaker>,
+    ) -> Self {
+        Self {
+            client,
+            config,
+            retry: options.retry.clone(),
+            concurrency,
+            breaker,
+            compress: options.compress,
+            shutdown: None,
+            events: options.events.clone(),
+            tasks: JoinSet::new(),
+        }
+    }
+
+    /// Stops fetching once `shutdown` says so: fetches stop where
+    /// they are, and those yet to start never are.
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// How logos are requested.
+    pub fn config(&self) -> &LogoConfig {
+        &self.config
+    }
+
+    /// The concurrency limit fetches share.
+    pub fn concurrency(&self) -> &Concurrency {
+        &self.concurrency
+    }
+
+    /// Whether fetching was given up on because the logo CDN kept
+    /// failing.
+    pub fn gave_up(&self) -> bool {
+        self.breaker.gave_up()
+    }
+
+    /// How many fetches haven't been [collected](Self::next) yet.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Whether every fetch has been [collected](Self::next).
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Starts fetching a logo (once the concurrency limit allows).
+    pub fn spawn(&mut self, job: LogoJob) {
+        let client = self.client.clone();
+        let config = self.config.clone();
+        let retry = self.retry.clone();
+        let concurrency = self.concurrency.clone();
+        let breaker = self.breaker.clone();
+        let compress = self.compress;
+        let shutdown = self.shutdown.clone();
+        let events = self.events.clone();
+        let LogoJob {
+            symbol,
+            target,
+            validators,
+        } = job;
+
+        self.tasks.spawn(async move {
+            let finish = |result: FetchResult| {
+                if let Some(events) = &events {
+                    events.send(FetchEvent::finished(&result.record()));
+                }
+                result
+            };
+            let not_attempted = || {
+                finish(FetchResult {
+                    symbol: symbol.clone(),
+                    fetched: None,
+                })
+            };
+
+            // on shutdown, fetches stop where they are (whatever they
+            // left in temporary files is for the caller to clean up)
+            let permit = tokio::select! {
+                permit = concurrency.acquire() => permit,
+                () = requested(shutdown.as_ref()) => return not_attempted(),
+            };
+            let entered = tokio::select! {
+                entered = breaker.enter() => entered,
+                () = requested(shutdown.as_ref()) => None,
+            };
+            let Some(pass) = entered else {
+                concurrency.release(permit, false);
+                return not_attempted();
+            };
+            if let Some(events) = &events {
+                events.send(FetchEvent::SymbolStarted {
+                    symbol: symbol.clone(),
+                });
+            }
+
+            let started = Instant::now();
+            let fetched = retry.run(|| async {
+                let result = match &target {
+                    Target::Dir(dest) => match create_dir(dest).await {
+                        Err(e) => Err(e),
+                        Ok(()) => match compress {
+                            Some(level) => {
+                                crate::fetch_logo_compressed(
+                                    &client,
+                                    &symbol,
+                                    dest,
+                                    level,
+                                    &config,
+                                    validators.as_ref(),
+                                )
+                                .await
+                            }
+                            None => {
+                                crate::fetch_logo(
+                                    &client,
+                                    &symbol,
+                                    dest,
+                                    &config,
+                                    validators.as_ref(),
+                                )
+                                .await
+                            }
+                        },
+                    },
+                    Target::Archive(archive) => {
+                        archive_logo(&client, &symbol, archive, compress, &config).await
+                    }
+                    Target::Check => crate::check_logo(&client, &symbol, &config).await,
+                };
+
+                if retry::is_rate_limited(&result) {
+                    concurrency.rate_limited();
+                }
+                result
+            });
+            // a pass dropped unrecorded (here, or as the task is aborted
+            // when the batch is dropped) is released by its `Drop`, so a
+            // cancelled probe doesn't hold up the fetches after it
+            let (result, attempts) = tokio::select! {
+                fetched = fetched => fetched,
+                () = requested(shutdown.as_ref()) => return not_attempted(),
+            };
+            let duration = started.elapsed();
+            breaker.record(pass, retry::is_transient(&result));
+            concurrency.release(permit, attempts == 1 && !retry::is_transient(&result));
+
+            if !matches!(target, Target::Check) {
+                log_result(&symbol, &target, &result, attempts);
+            }
+
+            finish(FetchResult {
+                symbol,
+                fetched: Some(Fetched {
+                    result,
+                    attempts,
+                    duration,
+                }),
+            })
+        });
+    }
+
+    /// Waits for the next fetch to finish (in whatever order they
+    /// do), or returns `None` once every one has been collected.
+    pub async fn next(&mut self) -> Option<FetchResult> {
+        StreamExt::next(self).await
+    }
+}
+
+/// The fetches' results, as they finish. Dropping the batch cancels
+/// those that haven't.
+impl Stream for Batch {
+    type Item = FetchResult;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<FetchResult>> {
+        loop {
+            match self.tasks.poll_join_next(cx) {
+                Poll::Ready(Some(Ok(result))) => return Poll::Ready(Some(result)),
+                Poll::Ready(Some(Err(e))) => warn!("a logo fetch task failed: {e}"),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.tasks.len()))
+    }
+}
+
+/// Waits until `shutdown` (if any) is requested.
+async fn requested(shutdown: Option<&Shutdown>) {
+    match shutdown {
+        Some(shutdown) => shutdown.requested().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Downloads a logo and sends it to the archive writer task instead
+/// of writing it to disk. The returned outcome's path is the logo's
+/// name within the archive.
+async fn archive_logo(
+    client: &reqwest::Client,
+    symbol: &str,
+    archive: &EntrySender,
+    compress_level: Option<u32>,
+    config: &LogoConfig,
+) -> Result<LogoOutcome> {
+    let logo = match crate::download_logo(client, symbol, config).await? {
+        LogoOutcome::Downloaded(logo) => logo,
+        outcome => return Ok(outcome),
+    };
+
+    let (name, content) = match compress_level {
+        Some(level) => (
+            format!("{}z", logo.file_name),
+            compress::gzip(&logo.content, level),
+        ),
+        None => (logo.file_name.clone(), logo.content.clone()),
+    };
+    let outcome = logo.fetched(PathBuf::from(&name), &content);
+
+    // if the writer has failed, its error is reported once fetching
+    // is done
+    let _ = archive.send((name, content)).await;
+
+    Ok(outcome)
+}
+
+/// Logs what became of a fetch.
+fn log_result(symbol: &str, target: &Target, result: &Result<LogoOutcome>, attempts: u32) {
+    let attempts = match attempts {
+        1 => "1 attempt".to_string(),
+        n => format!("{n} attempts"),
+    };
+
+    match result {
+        Ok(LogoOutcome::Fetched {
+            path, url, status, ..
+        }) => {
+            trace!(
+                symbol,
+                url = url.as_str(),
+                status = status.as_u16(),
+                path = path.to_string_lossy().as_ref();
+                "{} '{}'",
+                match target {
+                    Target::Archive(_) => "archived logo as",
+                    _ => "wrote logo to",
+                },
+                path.display()
+            );
+        }
+        Ok(LogoOutcome::NotModified { url }) => {
+            trace!(
+                symbol,
+                url = url.as_str();
+                "logo for '{symbol}' is up to date"
+            );
+        }
+        Ok(LogoOutcome::InvalidSymbol) => {
+            warn!(
+                symbol;
+                "skipping symbol '{symbol}' with no logo URL candidates"
+            );
+        }
+        Ok(LogoOutcome::HttpError {
+            attempts: url_attempts,
+            ..
+        }) => {
+            let (url, status) = url_attempts
+                .last()
+                .map_or(("", 0), |(url, status)| (url.as_str(), status.as_u16()));
+            warn!(
+                symbol,
+                url,
+                status;
+                "failed to fetch logo for '{symbol}' after {attempts} (tried {})",
+                url_attempts
+                    .iter()
+                    .map(|(url, status)| format!("'{url}': {status}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Ok(_) => {}
+        Err(e) => warn!(
+            symbol;
+            "failed to fetch logo for '{symbol}' after {attempts}: {e}"
+        ),
+    }
+}
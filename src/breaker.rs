@@ -0,0 +1,413 @@
+//! Pausing logo fetches while the CDN keeps failing.
+
+use std::sync::Mutex;
+
+use log::{error, info, warn};
+use tokio::{
+    sync::Notify,
+    time::{Duration, Instant},
+};
+
+/// A circuit breaker shared between fetch tasks. After `threshold`
+/// transient failures in a row it trips: new fetches wait out a
+/// cool-down, after which a single probe is let through. If the
+/// probe succeeds, fetching resumes; if it fails, the breaker trips
+/// again, and after `max_trips` trips in a row it gives up for good.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cool_down: Duration,
+    max_trips: u32,
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Transient failures in a row since the last success.
+    failures: u32,
+    /// Trips in a row since the last success.
+    trips: u32,
+    /// While tripped, when the probe may be let through.
+    open_until: Option<Instant>,
+    /// Whether the probe is in flight.
+    probing: bool,
+    gave_up: bool,
+}
+
+/// Permission to make a fetch, given by [`CircuitBreaker::enter`].
+/// Its result must be passed back to [`CircuitBreaker::record`].
+///
+/// A probe dropped without being recorded (e.g. as its fetch was
+/// cancelled) lets the next fetch through as the probe instead.
+#[derive(Debug)]
+#[must_use]
+pub struct Pass<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl Drop for Pass<'_> {
+    fn drop(&mut self) {
+        if !self.probe {
+            return;
+        }
+
+        let mut state = self.breaker.lock();
+        if state.probing && !state.gave_up {
+            state.probing = false;
+            state.open_until = Some(Instant::now());
+            self.breaker.changed.notify_waiters();
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// A breaker tripping after `threshold` transient failures in a
+    /// row (or never, if it's 0), pausing for `cool_down` each time,
+    /// and giving up after `max_trips` trips in a row (at least 1).
+    pub fn new(threshold: u32, cool_down: Duration, max_trips: u32) -> Self {
+        Self {
+            threshold,
+            cool_down,
+            max_trips: max_trips.max(1),
+            state: Mutex::default(),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Waits until a fetch may be made, or returns `None` if the
+    /// breaker has given up.
+    pub async fn enter(&self) -> Option<Pass<'_>> {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let open_until = {
+                let mut state = self.lock();
+                if state.gave_up {
+                    return None;
+                }
+
+                match state.open_until {
+                    Some(until) if Instant::now() >= until => {
+                        state.open_until = None;
+                        state.probing = true;
+                        info!("probing whether the logo CDN has recovered");
+                        return Some(Pass {
+                            breaker: self,
+                            probe: true,
+                        });
+                    }
+                    Some(until) => Some(until),
+                    None if state.probing => None,
+                    None => {
+                        return Some(Pass {
+                            breaker: self,
+                            probe: false,
+                        })
+                    }
+                }
+            };
+
+            match open_until {
+                Some(until) => {
+                    tokio::select! {
+                        () = changed => {}
+                        () = tokio::time::sleep_until(until) => {}
+                    }
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    /// Records how a fetch made with `pass` went; `failed` if it
+    /// failed [transiently](crate::retry::is_transient).
+    ///
+    /// While tripped, failures of fetches that were already in
+    /// flight are ignored (only the probe's counts), but any success
+    /// resumes fetching.
+    pub fn record(&self, mut pass: Pass<'_>, failed: bool) {
+        // recorded, so not released when dropped
+        let probe = std::mem::take(&mut pass.probe);
+        if self.threshold == 0 {
+            return;
+        }
+
+        let mut state = self.lock();
+        if state.gave_up {
+            return;
+        }
+
+        if !failed {
+            if state.open_until.is_some() || state.probing {
+                info!("the logo CDN is responding again; resuming");
+                self.changed.notify_waiters();
+            }
+            *state = State::default();
+            return;
+        }
+
+        if state.open_until.is_some() || (state.probing && !probe) {
+            return;
+        }
+
+        state.failures += 1;
+        if !probe && state.failures < self.threshold {
+            return;
+        }
+
+        state.failures = 0;
+        state.probing = false;
+        state.trips += 1;
+
+        let cause = if probe {
+            "the logo CDN is still failing".to_string()
+        } else {
+            format!("{} logo fetches failed in a row", self.threshold)
+        };
+
+        if state.trips >= self.max_trips {
+            state.gave_up = true;
+            error!("{cause}; giving up");
+        } else {
+            state.open_until = Some(Instant::now() + self.cool_down);
+            warn!(
+                "{cause}; pausing all fetches for {}s before trying again",
+                self.cool_down.as_secs()
+            );
+        }
+
+        self.changed.notify_waiters();
+    }
+
+    /// Whether the breaker has given up.
+    pub fn gave_up(&self) -> bool {
+        self.lock().gave_up
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    const COOL_DOWN: Duration = Duration::from_secs(30);
+
+    /// Makes a fetch through the breaker, failing as `failed` says.
+    async fn fetch(breaker: &CircuitBreaker, failed: bool) -> Option<bool> {
+        let pass = breaker.enter().await?;
+        let probe = pass.probe;
+        breaker.record(pass, failed);
+        Some(probe)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failures_below_the_threshold_dont_trip() {
+        let breaker = CircuitBreaker::new(3, COOL_DOWN, 2);
+        let started = Instant::now();
+
+        fetch(&breaker, true).await.unwrap();
+        fetch(&breaker, true).await.unwrap();
+        assert_eq!(fetch(&breaker, false).await, Some(false));
+
+        // the success reset the count
+        fetch(&breaker, true).await.unwrap();
+        fetch(&breaker, true).await.unwrap();
+        assert_eq!(fetch(&breaker, false).await, Some(false));
+
+        assert_eq!(started.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tripping_pauses_for_the_cool_down_then_probes() {
+        let breaker = CircuitBreaker::new(3, COOL_DOWN, 2);
+        for _ in 0..3 {
+            fetch(&breaker, true).await.unwrap();
+        }
+
+        let started = Instant::now();
+        assert_eq!(fetch(&breaker, false).await, Some(true));
+        assert_eq!(started.elapsed(), COOL_DOWN);
+
+        // the successful probe resumed fetching
+        assert_eq!(fetch(&breaker, false).await, Some(false));
+        assert_eq!(started.elapsed(), COOL_DOWN);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_probes_trip_again_until_giving_up() {
+        let breaker = CircuitBreaker::new(2, COOL_DOWN, 3);
+        fetch(&breaker, true).await.unwrap();
+        fetch(&breaker, true).await.unwrap();
+
+        let started = Instant::now();
+        assert_eq!(fetch(&breaker, true).await, Some(true));
+        assert!(!breaker.gave_up());
+        assert_eq!(fetch(&breaker, true).await, Some(true));
+        assert!(breaker.gave_up());
+        assert_eq!(started.elapsed(), 2 * COOL_DOWN);
+
+        assert_eq!(fetch(&breaker, false).await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_the_probe_is_let_through_while_tripped() {
+        let breaker = Arc::new(CircuitBreaker::new(1, COOL_DOWN, 2));
+        fetch(&breaker, true).await.unwrap();
+
+        // hold the probe's pass while the others wait
+        tokio::time::advance(COOL_DOWN).await;
+        let probe = breaker.enter().await.unwrap();
+        assert!(probe.probe);
+
+        let entered = Arc::new(AtomicU32::new(0));
+        let waiting = (0..3)
+            .map(|_| {
+                let (breaker, entered) = (breaker.clone(), entered.clone());
+                tokio::spawn(async move {
+                    let pass = breaker.enter().await.unwrap();
+                    entered.fetch_add(1, Ordering::SeqCst);
+                    breaker.record(pass, false);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        tokio::time::sleep(COOL_DOWN * 10).await;
+        assert_eq!(entered.load(Ordering::SeqCst), 0);
+
+        breaker.record(probe, false);
+        for task in waiting {
+            task.await.unwrap();
+        }
+        assert_eq!(entered.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_dropped_probe_lets_the_next_fetch_probe() {
+        let breaker = Arc::new(CircuitBreaker::new(1, COOL_DOWN, 2));
+        fetch(&breaker, true).await.unwrap();
+
+        tokio::time::advance(COOL_DOWN).await;
+        let probe = breaker.enter().await.unwrap();
+        assert!(probe.probe);
+
+        let waiting = {
+            let breaker = breaker.clone();
+            tokio::spawn(async move { fetch(&breaker, false).await })
+        };
+        tokio::time::sleep(COOL_DOWN).await;
+        assert!(!waiting.is_finished());
+
+        // as if its fetch was cancelled
+        drop(probe);
+        let started = Instant::now();
+        assert_eq!(waiting.await.unwrap(), Some(true));
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        assert_eq!(fetch(&breaker, false).await, Some(false));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn in_flight_failures_dont_count_while_tripped() {
+        let breaker = CircuitBreaker::new(2, COOL_DOWN, 2);
+        let in_flight = breaker.enter().await.unwrap();
+        fetch(&breaker, true).await.unwrap();
+        fetch(&breaker, true).await.unwrap();
+
+        // would otherwise count towards the next trip
+        breaker.record(in_flight, true);
+
+        assert_eq!(fetch(&breaker, false).await, Some(true));
+        assert!(!breaker.gave_up());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_zero_threshold_never_trips() {
+        let breaker = CircuitBreaker::new(0, COOL_DOWN, 1);
+        let started = Instant::now();
+
+        for _ in 0..100 {
+            assert_eq!(fetch(&breaker, true).await, Some(false));
+        }
+        assert!(!breaker.gave_up());
+        assert_eq!(started.elapsed(), Duration::ZERO);
+    }
+
+    /// A CDN that fails every request until `recovers_at`.
+    struct MockCdn {
+        recovers_at: Instant,
+        requests: AtomicU32,
+    }
+
+    impl MockCdn {
+        async fn get(&self) -> bool {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Instant::now() >= self.recovers_at
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_fetches_ride_out_an_outage() {
+        let breaker = Arc::new(CircuitBreaker::new(5, COOL_DOWN, 10));
+        let cdn = Arc::new(MockCdn {
+            recovers_at: Instant::now() + Duration::from_secs(100),
+            requests: AtomicU32::new(0),
+        });
+
+        let tasks = (0..4)
+            .map(|_| {
+                let (breaker, cdn) = (breaker.clone(), cdn.clone());
+                tokio::spawn(async move {
+                    let mut fetched = 0;
+                    while fetched < 10 {
+                        let pass = breaker.enter().await.expect("not given up");
+                        let ok = cdn.get().await;
+                        breaker.record(pass, !ok);
+                        fetched += u32::from(ok);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // a few failures to trip, then a probe per cool-down, rather
+        // than 4 fetches every 100ms throughout
+        let failed = cdn.requests.load(Ordering::SeqCst) - 40;
+        assert!(
+            failed <= 12,
+            "{failed} requests were made during the outage"
+        );
+        assert!(!breaker.gave_up());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_lasting_outage_gives_up() {
+        let breaker = Arc::new(CircuitBreaker::new(5, COOL_DOWN, 3));
+        let cdn = MockCdn {
+            recovers_at: Instant::now() + Duration::from_secs(3600),
+            requests: AtomicU32::new(0),
+        };
+
+        while let Some(pass) = breaker.enter().await {
+            let ok = cdn.get().await;
+            breaker.record(pass, !ok);
+        }
+
+        assert!(breaker.gave_up());
+        // 5 to trip, then 2 failed probes
+        assert_eq!(cdn.requests.load(Ordering::SeqCst), 7);
+    }
+}
@@ -0,0 +1,102 @@
+//! A cache of downloaded symbol lists, shared by every run (whatever
+//! its output directory) of the same user.
+//!
+//! Each list is saved, as downloaded, under a name derived from its
+//! URL; when it was saved is the file's modification time. Lists are
+//! written to a temporary file and renamed into place (see
+//! [`fetch_symbol_list_cached`](crate::fetch_symbol_list_cached)), so
+//! that concurrent runs never see one half-written, and a list
+//! disappearing (e.g. because another run [cleared](SymbolCache::clear)
+//! the cache) only means it isn't cached.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::hash;
+
+/// A directory of cached symbol lists.
+#[derive(Debug, Clone)]
+pub struct SymbolCache {
+    dir: PathBuf,
+}
+
+impl SymbolCache {
+    /// The cache in the platform's cache directory (e.g.
+    /// `$XDG_CACHE_HOME/nyse-logos` on Linux, `~/Library/Caches/nyse-logos`
+    /// on macOS or `%LOCALAPPDATA%\nyse-logos\cache` on Windows), or
+    /// `None` if there isn't one (e.g. no home directory is set).
+    pub fn user() -> Option<Self> {
+        let dirs = directories::ProjectDirs::from_path(PathBuf::from("nyse-logos"))?;
+        Some(Self::at(dirs.cache_dir()))
+    }
+
+    /// The cache in the given directory.
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory the cache is in (which may not exist yet).
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the list downloaded from `url` is cached: the last
+    /// segment of its path, prefixed with a hash of the whole URL (so
+    /// that lists of the same name from different places don't clash),
+    /// e.g. `1f0e3dad99908345-nyse.txt`.
+    pub fn path(&self, url: &str) -> PathBuf {
+        let name = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                url.path_segments()?
+                    .next_back()
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| "symbols".to_string());
+        let digest = hash::sha256_hex(url.as_bytes());
+
+        self.dir.join(format!("{}-{name}", &digest[..16]))
+    }
+
+    /// How long ago the list downloaded from `url` was cached, or
+    /// `None` if it isn't.
+    pub async fn age(&self, url: &str) -> Option<Duration> {
+        let modified = tokio::fs::metadata(self.path(url))
+            .await
+            .and_then(|meta| meta.modified())
+            .ok()?;
+        Some(modified.elapsed().unwrap_or_default())
+    }
+
+    /// Removes every cached list, returning how many there were.
+    /// Temporary files of lists still being downloaded are left alone,
+    /// as are files removed by someone else meanwhile.
+    pub async fn clear(&self) -> io::Result<usize> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') && name.ends_with(".tmp") {
+                continue;
+            }
+
+            match tokio::fs::remove_file(entry.path()).await {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(removed)
+    }
+}
@@ -0,0 +1,65 @@
+//! Reporting of symbols added or removed since the previous run.
+
+use std::{collections::HashSet, path::Path};
+
+use serde::Serialize;
+
+use crate::symbol::Symbol;
+
+/// The contents of `changes.toml`.
+#[derive(Debug, Default, Serialize)]
+pub struct Changes {
+    /// Tickers that weren't in the previous symbol list.
+    pub added: Vec<String>,
+    /// Tickers that are no longer in the symbol list.
+    pub removed: Vec<String>,
+    /// Logo files on disk that belong to removed tickers.
+    pub orphaned_logos: Vec<String>,
+}
+
+impl Changes {
+    /// Compares the previous run's symbols with the current ones,
+    /// keyed on the uppercase ticker. Orphaned logos are resolved
+    /// against `output`.
+    pub fn between(previous: &[Symbol], current: &[Symbol], output: &Path) -> Self {
+        let key = |s: &Symbol| s.ticker.trim().to_uppercase();
+
+        let previous_keys = previous.iter().map(key).collect::<HashSet<_>>();
+        let current_keys = current.iter().map(key).collect::<HashSet<_>>();
+
+        let mut changes = Self::default();
+
+        for symbol in current {
+            let ticker = key(symbol);
+            if !previous_keys.contains(&ticker) && !changes.added.contains(&ticker) {
+                changes.added.push(ticker);
+            }
+        }
+
+        for symbol in previous {
+            let ticker = key(symbol);
+            if current_keys.contains(&ticker) || changes.removed.contains(&ticker) {
+                continue;
+            }
+
+            if let Some(logo_file) = &symbol.logo_file {
+                let logo_path = output.join(logo_file);
+                if logo_path.exists() {
+                    changes.orphaned_logos.push(logo_path.display().to_string());
+                }
+            }
+
+            changes.removed.push(ticker);
+        }
+
+        changes.added.sort();
+        changes.removed.sort();
+        changes.orphaned_logos.sort();
+
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
@@ -0,0 +1,43 @@
+//! Gzip compression of logos (`.svgz`).
+
+use std::{io::Write, path::Path};
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::{atomic, Error, Result};
+
+/// Gzips `content` at the given level (0-9).
+pub fn gzip(content: &[u8], level: u32) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+    encoder
+        .write_all(content)
+        .and_then(|()| encoder.finish())
+        .expect("writing to a Vec doesn't fail")
+}
+
+/// Gzips the file at `src` into `dest` (atomically) and removes
+/// `src`, returning the compressed content.
+pub async fn compress_file(src: &Path, dest: &Path, level: u32) -> Result<Vec<u8>> {
+    let content = tokio::fs::read(src).await.map_err(|source| Error::Io {
+        path: src.to_path_buf(),
+        source,
+    })?;
+
+    let compressed = gzip(&content, level);
+
+    atomic::write(dest, &compressed)
+        .await
+        .map_err(|source| Error::Io {
+            path: dest.to_path_buf(),
+            source,
+        })?;
+
+    tokio::fs::remove_file(src)
+        .await
+        .map_err(|source| Error::Io {
+            path: src.to_path_buf(),
+            source,
+        })?;
+
+    Ok(compressed)
+}
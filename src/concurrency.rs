@@ -0,0 +1,287 @@
+//! Limiting (and adapting) how many logos are fetched at once.
+
+use std::{sync::Mutex, time::Duration};
+
+use log::debug;
+use tokio::{
+    sync::{Semaphore, SemaphorePermit},
+    time::Instant,
+};
+
+/// The least time between two reductions of an adaptive limit, so
+/// that a burst of rate limited responses (from requests made before
+/// the first reduction took effect) only counts once.
+const DECREASE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A limit on how many fetches run at once, shared between tasks.
+///
+/// An adaptive limit starts at its maximum, halves whenever fetches
+/// are rate limited, and grows back by one after each run of (as
+/// many as the current limit) healthy fetches.
+#[derive(Debug)]
+pub struct Concurrency {
+    semaphore: Semaphore,
+    max: usize,
+    adaptive: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    limit: usize,
+    /// Permits still to be taken out of circulation (as they're
+    /// released) after the limit was lowered.
+    debt: usize,
+    /// Healthy fetches since the limit last changed.
+    healthy: usize,
+    last_decrease: Option<Instant>,
+    lowest: usize,
+    highest: usize,
+}
+
+/// The range a [`Concurrency`] limit moved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcurrencyStats {
+    pub lowest: usize,
+    pub highest: usize,
+    pub current: usize,
+}
+
+impl Concurrency {
+    /// A limit of `jobs` (at least 1) that never changes.
+    pub fn fixed(jobs: usize) -> Self {
+        Self::new(jobs, false)
+    }
+
+    /// A limit of up to `jobs` (at least 1) that adapts to rate
+    /// limiting.
+    pub fn adaptive(jobs: usize) -> Self {
+        Self::new(jobs, true)
+    }
+
+    fn new(jobs: usize, adaptive: bool) -> Self {
+        let jobs = jobs.max(1);
+
+        Self {
+            semaphore: Semaphore::new(jobs),
+            max: jobs,
+            adaptive,
+            state: Mutex::new(State {
+                limit: jobs,
+                debt: 0,
+                healthy: 0,
+                last_decrease: None,
+                lowest: jobs,
+                highest: jobs,
+            }),
+        }
+    }
+
+    /// Waits for a fetch to be allowed to start.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.semaphore
+            .acquire()
+            .await
+            .expect("semaphore is never closed")
+    }
+
+    /// Records that a request was rate limited (`429`, or `503`),
+    /// halving an adaptive limit.
+    pub fn rate_limited(&self) {
+        if !self.adaptive {
+            return;
+        }
+
+        let mut state = self.lock();
+        let now = Instant::now();
+        if state
+            .last_decrease
+            .is_some_and(|last| now.duration_since(last) < DECREASE_INTERVAL)
+        {
+            return;
+        }
+
+        state.last_decrease = Some(now);
+        state.healthy = 0;
+
+        let limit = (state.limit / 2).max(1);
+        if limit == state.limit {
+            return;
+        }
+
+        state.debt += state.limit - limit;
+        state.debt -= self.semaphore.forget_permits(state.debt);
+        debug!(
+            "rate limited; lowering concurrency from {} to {limit}",
+            state.limit
+        );
+        state.limit = limit;
+        state.lowest = state.lowest.min(limit);
+    }
+
+    /// Ends a fetch started with [`acquire`](Self::acquire);
+    /// `healthy` if it went through without being rate limited or
+    /// failing transiently, which may grow an adaptive limit.
+    pub fn release(&self, permit: SemaphorePermit<'_>, healthy: bool) {
+        let mut state = self.lock();
+
+        if self.adaptive && healthy && state.limit < self.max {
+            state.healthy += 1;
+            if state.healthy >= state.limit {
+                state.healthy = 0;
+                state.limit += 1;
+                state.highest = state.highest.max(state.limit);
+                debug!(
+                    "responses are healthy; raising concurrency to {}",
+                    state.limit
+                );
+
+                if state.debt > 0 {
+                    state.debt -= 1;
+                } else {
+                    self.semaphore.add_permits(1);
+                }
+            }
+        }
+
+        if state.debt > 0 {
+            state.debt -= 1;
+            permit.forget();
+        }
+    }
+
+    /// The lowest, highest and current limit so far.
+    pub fn stats(&self) -> ConcurrencyStats {
+        let state = self.lock();
+        ConcurrencyStats {
+            lowest: state.lowest,
+            highest: state.highest,
+            current: state.limit,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `count` fetches one after the other, each healthy or not.
+    async fn fetch(concurrency: &Concurrency, count: usize, healthy: bool) {
+        for _ in 0..count {
+            let permit = concurrency.acquire().await;
+            concurrency.release(permit, healthy);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limits_halve_the_limit() {
+        let concurrency = Concurrency::adaptive(8);
+
+        concurrency.rate_limited();
+        assert_eq!(concurrency.stats().current, 4);
+        assert_eq!(concurrency.semaphore.available_permits(), 4);
+
+        // a burst of rate limits only counts once
+        concurrency.rate_limited();
+        assert_eq!(concurrency.stats().current, 4);
+
+        tokio::time::advance(DECREASE_INTERVAL).await;
+        concurrency.rate_limited();
+        assert_eq!(concurrency.stats().current, 2);
+        assert_eq!(concurrency.semaphore.available_permits(), 2);
+
+        for _ in 0..3 {
+            tokio::time::advance(DECREASE_INTERVAL).await;
+            concurrency.rate_limited();
+        }
+        assert_eq!(concurrency.stats().current, 1);
+        assert_eq!(concurrency.semaphore.available_permits(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permits_in_use_are_forgotten_as_they_are_released() {
+        let concurrency = Concurrency::adaptive(4);
+        let mut permits = Vec::new();
+        for _ in 0..4 {
+            permits.push(concurrency.acquire().await);
+        }
+
+        concurrency.rate_limited();
+        assert_eq!(concurrency.stats().current, 2);
+
+        concurrency.release(permits.pop().unwrap(), false);
+        concurrency.release(permits.pop().unwrap(), false);
+        assert_eq!(concurrency.semaphore.available_permits(), 0);
+
+        concurrency.release(permits.pop().unwrap(), false);
+        concurrency.release(permits.pop().unwrap(), false);
+        assert_eq!(concurrency.semaphore.available_permits(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn healthy_fetches_grow_the_limit_back() {
+        let concurrency = Concurrency::adaptive(8);
+        concurrency.rate_limited();
+        assert_eq!(concurrency.stats().current, 4);
+
+        // it takes as many healthy fetches as the current limit
+        fetch(&concurrency, 3, true).await;
+        assert_eq!(concurrency.stats().current, 4);
+        fetch(&concurrency, 1, true).await;
+        assert_eq!(concurrency.stats().current, 5);
+        assert_eq!(concurrency.semaphore.available_permits(), 5);
+
+        // unhealthy ones don't count
+        fetch(&concurrency, 10, false).await;
+        assert_eq!(concurrency.stats().current, 5);
+
+        fetch(&concurrency, 5 + 6 + 7, true).await;
+        assert_eq!(concurrency.stats().current, 8);
+
+        // but never past the maximum
+        fetch(&concurrency, 100, true).await;
+        assert_eq!(concurrency.stats().current, 8);
+        assert_eq!(concurrency.semaphore.available_permits(), 8);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stats_track_the_range_of_the_limit() {
+        let concurrency = Concurrency::adaptive(8);
+        assert_eq!(
+            concurrency.stats(),
+            ConcurrencyStats {
+                lowest: 8,
+                highest: 8,
+                current: 8,
+            }
+        );
+
+        concurrency.rate_limited();
+        tokio::time::advance(DECREASE_INTERVAL).await;
+        concurrency.rate_limited();
+        fetch(&concurrency, 2, true).await;
+
+        assert_eq!(
+            concurrency.stats(),
+            ConcurrencyStats {
+                lowest: 2,
+                highest: 8,
+                current: 3,
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fixed_limits_ignore_rate_limits() {
+        let concurrency = Concurrency::fixed(4);
+        concurrency.rate_limited();
+        fetch(&concurrency, 4, true).await;
+
+        assert_eq!(concurrency.stats().current, 4);
+        assert_eq!(concurrency.semaphore.available_permits(), 4);
+    }
+}
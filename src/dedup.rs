@@ -0,0 +1,205 @@
+//! Linking byte-identical logos together rather than keeping copies.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use log::{trace, warn};
+
+use crate::atomic;
+
+/// What to do with a logo that's byte-identical to one already
+/// written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupMode {
+    /// Keep a separate copy.
+    #[default]
+    Copy,
+    /// Replace it with a hard link to the first.
+    Hardlink,
+    /// Replace it with a (relative) symbolic link to the first.
+    Symlink,
+}
+
+impl std::str::FromStr for DedupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "copy" => Ok(Self::Copy),
+            "hardlink" => Ok(Self::Hardlink),
+            "symlink" => Ok(Self::Symlink),
+            _ => Err(format!(
+                "invalid dedup mode '{s}' (expected hardlink, symlink or copy)"
+            )),
+        }
+    }
+}
+
+/// Tracks the logos written in a run by digest, linking each
+/// duplicate to the first logo written with the same contents.
+#[derive(Debug)]
+pub struct Deduplicator {
+    mode: DedupMode,
+    /// The first path written with each digest.
+    seen: HashMap<String, PathBuf>,
+    /// Whether linking failed (and so was given up on) yet.
+    failed: bool,
+}
+
+impl Deduplicator {
+    pub fn new(mode: DedupMode) -> Self {
+        Self {
+            mode,
+            seen: HashMap::new(),
+            failed: false,
+        }
+    }
+
+    /// Records that the logo at `path`, with the given digest, was
+    /// written, and links it to an earlier logo with the same digest
+    /// if there is one. Returns the path of the logo it was linked
+    /// to, if it was.
+    ///
+    /// If linking fails (e.g. as the filesystem doesn't support it),
+    /// the logo is left as a copy, and a warning is logged the first
+    /// time.
+    pub async fn dedup(&mut self, path: &Path, sha256: &str) -> Option<PathBuf> {
+        let canonical = match self.seen.get(sha256) {
+            Some(canonical) if canonical != path => canonical.clone(),
+            Some(_) => return None,
+            None => {
+                self.seen.insert(sha256.to_string(), path.to_path_buf());
+                return None;
+            }
+        };
+
+        if self.mode == DedupMode::Copy || self.failed {
+            return None;
+        }
+
+        match link(self.mode, &canonical, path).await {
+            Ok(()) => {
+                trace!(
+                    "linked '{}' to identical '{}'",
+                    path.display(),
+                    canonical.display()
+                );
+                Some(canonical)
+            }
+            Err(e) => {
+                self.failed = true;
+                warn!(
+                    "failed to link '{}' to identical '{}' ({e}); keeping copies of \
+                     duplicate logos instead",
+                    path.display(),
+                    canonical.display()
+                );
+                None
+            }
+        }
+    }
+}
+
+/// The symbolic links among `paths`, by the canonical path of the
+/// file each points to (dangling links are left out).
+///
+/// Deleting one of those files would leave its links dangling; see
+/// [`remove_linked`].
+pub async fn symlinks_by_target(
+    paths: impl IntoIterator<Item = PathBuf>,
+) -> io::Result<HashMap<PathBuf, Vec<PathBuf>>> {
+    let mut links = HashMap::<_, Vec<_>>::new();
+    for path in paths {
+        if !tokio::fs::symlink_metadata(&path).await?.is_symlink() {
+            continue;
+        }
+        if let Ok(target) = tokio::fs::canonicalize(&path).await {
+            links.entry(target).or_default().push(path);
+        }
+    }
+
+    Ok(links)
+}
+
+/// Removes the logo at `path` without leaving `links` (symbolic links
+/// to it) dangling: it's moved into the place of the first of them,
+/// and the rest are pointed at that.
+pub async fn remove_linked(path: &Path, links: &[PathBuf]) -> io::Result<()> {
+    let Some((first, rest)) = links.split_first() else {
+        return tokio::fs::remove_file(path).await;
+    };
+
+    // renaming over a symbolic link replaces the link, not its target
+    tokio::fs::rename(path, first).await?;
+    trace!(
+        "moved '{}' into the place of its link '{}'",
+        path.display(),
+        first.display()
+    );
+    for link_path in rest {
+        link(DedupMode::Symlink, first, link_path).await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces `path` with a link to `canonical`, atomically (by making
+/// the link at a temporary path and renaming it into place).
+async fn link(mode: DedupMode, canonical: &Path, path: &Path) -> io::Result<()> {
+    let tmp_path = atomic::tmp_path(path);
+    let target = match mode {
+        DedupMode::Symlink => relative_to(canonical, path),
+        _ => canonical.to_path_buf(),
+    };
+
+    let linked = {
+        let tmp_path = tmp_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = std::fs::remove_file(&tmp_path);
+            match mode {
+                DedupMode::Hardlink => std::fs::hard_link(&target, &tmp_path),
+                DedupMode::Symlink => symlink(&target, &tmp_path),
+                DedupMode::Copy => Ok(()),
+            }
+        })
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)))
+    };
+
+    if let Err(e) = linked {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(e);
+    }
+
+    atomic::commit(&tmp_path, path).await
+}
+
+/// The path to `target` relative to the directory `link` is in (or
+/// its absolute path if they're not in the same directory).
+fn relative_to(target: &Path, link: &Path) -> PathBuf {
+    match (target.parent(), target.file_name()) {
+        (Some(dir), Some(name)) if Some(dir) == link.parent() => PathBuf::from(name),
+        _ => std::path::absolute(target).unwrap_or_else(|_| target.to_path_buf()),
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symbolic links aren't supported on this platform",
+    ))
+}
@@ -0,0 +1,152 @@
+//! The library's error type.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::failure::FailureKind;
+
+/// An error fetching or parsing symbol lists and logos.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// An HTTP request failed to complete, or its body couldn't be read.
+    #[error("request to '{url}' failed: {source}")]
+    Http { url: String, source: reqwest::Error },
+    /// An HTTP request didn't complete within the client's timeout.
+    #[error("request to '{url}' timed out after {}s", after.as_secs())]
+    Timeout {
+        url: String,
+        after: Duration,
+        source: reqwest::Error,
+    },
+    /// A response was larger than the size cap allowed.
+    #[error("response from '{url}' is larger than {limit} bytes")]
+    TooLarge { url: String, limit: u64 },
+    /// A logo response was smaller than the minimum size (e.g. an
+    /// empty or truncated body).
+    #[error("response from '{url}' is only {bytes} bytes (expected at least {min})")]
+    TooSmall { url: String, bytes: u64, min: u64 },
+    /// A logo response isn't an SVG image (e.g. an HTML error page);
+    /// see [`svg::looks_like_svg`](crate::svg::looks_like_svg).
+    #[error("response from '{url}' is not an SVG")]
+    InvalidContent { url: String },
+    /// A logo response's `Content-Type` isn't SVG's (see
+    /// [`ContentTypeCheck`](crate::ContentTypeCheck)).
+    #[error("{}", match content_type {
+        Some(content_type) => format!(
+            "'{url}' responded with Content-Type '{content_type}' rather than 'image/svg+xml'"
+        ),
+        None => format!("'{url}' responded without a Content-Type"),
+    })]
+    ContentType {
+        url: String,
+        content_type: Option<String>,
+    },
+    /// A logo server responded with a non-success status.
+    #[error("'{url}' responded with HTTP {status}")]
+    Status {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+    /// A symbol list server responded with a non-success status.
+    #[error("symbol list at '{url}' responded with HTTP {status}")]
+    SymbolListHttp {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+    /// A symbol list is malformed: e.g. it has no headers, or two
+    /// columns of the same name. `line` is where (1-based), if it's
+    /// down to a single line.
+    #[error("malformed symbol list{}: {reason}", line.map(|line| format!(" (line {line})")).unwrap_or_default())]
+    SymbolListParse { line: Option<usize>, reason: String },
+    /// A symbol list has no column of the given name (matched
+    /// case-insensitively); `available` lists the ones it has.
+    #[error("symbol list has no '{column}' column (found columns: {})", available.join(", "))]
+    MissingColumn {
+        column: String,
+        available: Vec<String>,
+    },
+    /// A symbol's logo couldn't be fetched (see
+    /// [`FetchResult::into_result`](crate::batch::FetchResult::into_result)),
+    /// because of `source` if it's down to an error rather than the
+    /// responses.
+    #[error("failed to fetch logo for '{symbol}' ({kind})")]
+    LogoFetch {
+        symbol: String,
+        kind: FailureKind,
+        source: Option<Box<Error>>,
+    },
+    /// A filesystem operation failed.
+    #[error("'{}': {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A spreadsheet workbook couldn't be read.
+    #[error("failed to read workbook: {0}")]
+    Spreadsheet(#[from] calamine::Error),
+    /// A SQLite [store](crate::store::Store) operation failed.
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    /// A file the library reads or writes (e.g. the manifest) is
+    /// malformed, or couldn't be serialized.
+    #[error("{0}")]
+    Format(String),
+    /// A symbol list download or file isn't a symbol list at all
+    /// (e.g. an HTML error page).
+    #[error("{0}")]
+    UnexpectedContent(String),
+    /// A [`LogoFetcher`](crate::LogoFetcher) setting is out of range,
+    /// or doesn't make sense along with the others.
+    #[error("invalid {setting}: {reason}")]
+    InvalidConfig {
+        setting: &'static str,
+        reason: String,
+    },
+    /// A logo couldn't be [rendered](crate::render) as a PNG.
+    #[error("failed to render logo: {0}")]
+    Render(String),
+}
+
+/// A `Result` with [`Error`] as its error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// An [`Http`](Self::Http) error for a request to `url` that was
+    /// sent at `started`, or a [`Timeout`](Self::Timeout) if that's
+    /// why it failed.
+    pub(crate) fn http(url: &str, source: reqwest::Error, started: Instant) -> Self {
+        let url = url.to_string();
+        if source.is_timeout() {
+            Self::Timeout {
+                url,
+                after: started.elapsed(),
+                source,
+            }
+        } else {
+            Self::Http { url, source }
+        }
+    }
+
+    /// Whether this is down to a logo response's body not being a
+    /// usable logo ([too large](Self::TooLarge), [too
+    /// small](Self::TooSmall) or [not an SVG](Self::InvalidContent)),
+    /// so that another source may still have a good one.
+    pub fn is_unusable_logo(&self) -> bool {
+        matches!(
+            self,
+            Self::TooLarge { .. } | Self::TooSmall { .. } | Self::InvalidContent { .. }
+        )
+    }
+
+    /// A [`SymbolListParse`](Self::SymbolListParse) error not down to
+    /// any single line.
+    pub(crate) fn symbol_list(reason: impl Into<String>) -> Self {
+        Self::SymbolListParse {
+            line: None,
+            reason: reason.into(),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Events from fetching logos, for following a fetch's progress (e.g.
+//! in a GUI) without parsing its logs.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::sync::mpsc;
+
+use crate::{
+    failure::FailureKind,
+    results::{FetchRecord, Outcome, Summary},
+};
+
+/// Something that happened while fetching logos.
+#[derive(Debug, Clone)]
+pub enum FetchEvent {
+    /// Fetching began, for `total` symbols (including those that
+    /// turn out not to need fetching).
+    Started { total: usize },
+    /// A symbol's logo is being requested (its first attempt, once
+    /// the concurrency limit let it through).
+    SymbolStarted { symbol: String },
+    /// A symbol is done with: fetched, failed, skipped or never
+    /// attempted, as `outcome` says. `bytes` is the logo's size, if
+    /// one was fetched, and `duration` how long it took (including
+    /// retries).
+    SymbolFinished {
+        symbol: String,
+        outcome: Outcome,
+        failure: Option<FailureKind>,
+        bytes: Option<usize>,
+        duration: Duration,
+    },
+    /// Every symbol is done with.
+    Finished { summary: Summary },
+}
+
+impl FetchEvent {
+    /// A [`SymbolFinished`](Self::SymbolFinished) event for a symbol's
+    /// record.
+    pub fn finished(record: &FetchRecord) -> Self {
+        Self::SymbolFinished {
+            symbol: record.symbol.clone(),
+            outcome: record.outcome,
+            failure: record.failure,
+            bytes: record.bytes,
+            duration: Duration::from_millis(record.duration_ms.unwrap_or(0)),
+        }
+    }
+}
+
+/// Sends [`FetchEvent`]s over a bounded channel without ever waiting
+/// for room in it: events that don't fit (or that nobody's receiving)
+/// are dropped and [counted](Self::dropped), so that a slow consumer
+/// never holds up a fetch.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: mpsc::Sender<FetchEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventSender {
+    /// A sender, and the receiving end of its channel, which holds up
+    /// to `capacity` (at least 1) events not yet received.
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<FetchEvent>) {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let sender = Self {
+            sender,
+            dropped: Arc::default(),
+        };
+        (sender, receiver)
+    }
+
+    /// Sends an event, or drops it if the channel is full or closed.
+    pub fn send(&self, event: FetchEvent) {
+        if self.sender.try_send(event).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Sends an event, waiting for room in the channel if need be; for
+    /// events sent from outside the fetches that mustn't be lost (like
+    /// [`Finished`](FetchEvent::Finished) to a consumer waiting on it).
+    /// Does nothing if the channel is closed.
+    pub async fn send_wait(&self, event: FetchEvent) {
+        let _ = self.sender.send(event).await;
+    }
+
+    /// How many events have been dropped, by this sender and its
+    /// clones.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,153 @@
+//! The `nyse-logos` binary's exit codes, so that automation can tell
+//! why a run didn't succeed.
+
+use std::{fmt, process::ExitCode};
+
+use crate::results::Summary;
+
+/// How a run ended, and so what the process exits with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Status {
+    /// Everything succeeded (exit code 0).
+    Success,
+    /// The run couldn't get going: bad arguments, an unusable output
+    /// directory, an unreachable symbol list and the like (exit code
+    /// 1).
+    Fatal,
+    /// The run completed, but some logo fetches failed for reasons
+    /// other than the logo being missing (exit code 2).
+    FetchFailed,
+    /// The run was stopped early by Ctrl+C or SIGTERM (exit code 3).
+    Interrupted,
+    /// `verify`, `prune` or `--check` found problems with the output
+    /// directory or the logos upstream (exit code 4).
+    Problems,
+}
+
+impl Status {
+    /// The process exit code.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Fatal => 1,
+            Self::FetchFailed => 2,
+            Self::Interrupted => 3,
+            Self::Problems => 4,
+        }
+    }
+
+    /// How a fetch run that went to the end ended, given its
+    /// `totals`: [`FetchFailed`](Self::FetchFailed) if fetching was
+    /// given up on (see [`CircuitBreaker`](crate::breaker::CircuitBreaker)),
+    /// or if more than `threshold` symbols failed (not counting
+    /// missing logos, unless `fail_on_missing`), and otherwise
+    /// [`Success`](Self::Success).
+    pub fn of_fetches(
+        totals: &Summary,
+        gave_up: bool,
+        fail_on_missing: bool,
+        threshold: usize,
+    ) -> Self {
+        if gave_up || totals.failed(fail_on_missing) > threshold {
+            Self::FetchFailed
+        } else {
+            Self::Success
+        }
+    }
+
+    /// Whether this is [`Status::Success`].
+    pub fn is_success(self) -> bool {
+        self == Self::Success
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "success",
+            Self::Fatal => "fatal error",
+            Self::FetchFailed => "some logos failed",
+            Self::Interrupted => "interrupted",
+            Self::Problems => "problems found",
+        })
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        Self::from(status.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::failure::FailureKind;
+
+    #[test]
+    fn codes_are_as_documented() {
+        let codes = [
+            Status::Success,
+            Status::Fatal,
+            Status::FetchFailed,
+            Status::Interrupted,
+            Status::Problems,
+        ]
+        .map(Status::code);
+        assert_eq!(codes, [0, 1, 2, 3, 4]);
+        assert!(Status::Success.is_success());
+        assert!(!Status::Problems.is_success());
+    }
+
+    fn totals(failures: &[(FailureKind, usize)]) -> Summary {
+        Summary {
+            failures: failures.iter().copied().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fetches_without_failures_succeed() {
+        let status = Status::of_fetches(&totals(&[]), false, true, 0);
+        assert_eq!(status, Status::Success);
+    }
+
+    #[test]
+    fn missing_logos_only_fail_when_asked_to() {
+        let totals = totals(&[(FailureKind::NotFound, 3)]);
+        assert_eq!(
+            Status::of_fetches(&totals, false, false, 0),
+            Status::Success
+        );
+        assert_eq!(
+            Status::of_fetches(&totals, false, true, 0),
+            Status::FetchFailed
+        );
+        assert_eq!(Status::of_fetches(&totals, false, true, 3), Status::Success);
+    }
+
+    #[test]
+    fn failures_over_the_threshold_fail() {
+        let totals = totals(&[(FailureKind::NotFound, 5), (FailureKind::ServerError, 2)]);
+        assert_eq!(
+            Status::of_fetches(&totals, false, false, 0),
+            Status::FetchFailed
+        );
+        assert_eq!(
+            Status::of_fetches(&totals, false, false, 1),
+            Status::FetchFailed
+        );
+        assert_eq!(
+            Status::of_fetches(&totals, false, false, 2),
+            Status::Success
+        );
+    }
+
+    #[test]
+    fn giving_up_fails() {
+        assert_eq!(
+            Status::of_fetches(&totals(&[]), true, false, 100),
+            Status::FetchFailed
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! The `failed.toml` record of the symbols whose logos couldn't be
+//! fetched, for `--retry-failed` to try again.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{atomic, failure::FailureKind, Error, Result};
+
+/// The contents of `failed.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FailedList {
+    #[serde(default)]
+    pub failed: Vec<FailedEntry>,
+}
+
+/// A symbol whose logo couldn't be fetched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedEntry {
+    pub symbol: String,
+    pub kind: FailureKind,
+    /// What went wrong, if there's more to say than `kind`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// When the fetch failed, in seconds since the Unix epoch.
+    pub failed_at: u64,
+}
+
+impl FailedList {
+    /// Reads the list at `path`, or returns an empty one if it
+    /// doesn't exist.
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(Error::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+
+        toml::from_str(&content).map_err(|e| {
+            Error::Format(format!(
+                "failed to parse failed list at '{}': {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Records the outcomes of a run's fetches: drops the symbols
+    /// that were fetched (or otherwise no longer need to be), and
+    /// adds (or replaces the entries of) the ones that failed. Other
+    /// symbols (e.g. ones the run didn't attempt) are kept as they
+    /// were. The list ends up sorted by symbol.
+    pub fn update(
+        &mut self,
+        resolved: impl IntoIterator<Item = String>,
+        failed: impl IntoIterator<Item = FailedEntry>,
+    ) {
+        let mut by_symbol = self
+            .failed
+            .drain(..)
+            .map(|entry| (entry.symbol.clone(), entry))
+            .collect::<BTreeMap<_, _>>();
+
+        for symbol in resolved {
+            by_symbol.remove(&symbol);
+        }
+        for entry in failed {
+            by_symbol.insert(entry.symbol.clone(), entry);
+        }
+
+        self.failed = by_symbol.into_values().collect();
+    }
+
+    /// Writes the list to `path` atomically, or removes `path` if
+    /// nothing failed.
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let io_error = |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        if self.failed.is_empty() {
+            return match tokio::fs::remove_file(path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error(e)),
+                _ => Ok(()),
+            };
+        }
+
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| Error::Format(format!("failed to serialize failed list: {e}")))?;
+        atomic::write(path, content).await.map_err(io_error)
+    }
+}
@@ -0,0 +1,189 @@
+//! Classification of failed logo fetches.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, LogoOutcome, Result};
+
+/// Why a logo fetch failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// Every source responded `404` (or `410 Gone`); the logo is
+    /// simply missing.
+    NotFound,
+    /// A source responded `429` (or `503` with a `Retry-After`).
+    RateLimited,
+    /// A source responded with a `5xx` status.
+    ServerError,
+    /// A request timed out.
+    Timeout,
+    /// A connection couldn't be made, or broke off mid-response.
+    Connect,
+    /// A source responded with something other than an SVG (e.g. an
+    /// HTML error page), or with a suspiciously small body.
+    InvalidContent,
+    /// The logo couldn't be written to disk.
+    Io,
+    /// Anything else (e.g. an oversized logo, or a `403` from a
+    /// firewall blocking the requests).
+    Other,
+}
+
+impl FailureKind {
+    /// Classifies a fetch's result, or returns `None` if it didn't
+    /// fail.
+    pub fn classify(result: &Result<LogoOutcome>) -> Option<Self> {
+        match result {
+            Ok(LogoOutcome::HttpError { attempts, .. }) => {
+                let statuses = || attempts.iter().map(|(_, status)| *status);
+
+                Some(
+                    if statuses().any(|s| s == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                        Self::RateLimited
+                    } else if statuses().any(|s| s.is_server_error()) {
+                        Self::ServerError
+                    } else if statuses().all(is_missing) {
+                        Self::NotFound
+                    } else {
+                        Self::Other
+                    },
+                )
+            }
+            Ok(LogoOutcome::Placeholder { .. }) => Some(Self::NotFound),
+            Ok(_) => None,
+            Err(e) => Some(Self::of_error(e)),
+        }
+    }
+
+    /// Classifies an error (e.g. from downloading a symbol list).
+    pub fn of_error(e: &Error) -> Self {
+        match e {
+            Error::Timeout { .. } => Self::Timeout,
+            Error::Http { source, .. } if source.is_timeout() => Self::Timeout,
+            Error::Http { source, .. }
+                if source.is_connect() || source.is_request() || source.is_body() =>
+            {
+                Self::Connect
+            }
+            Error::Status { status, .. } | Error::SymbolListHttp { status, .. } => match *status {
+                reqwest::StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+                status if status.is_server_error() => Self::ServerError,
+                status if is_missing(status) => Self::NotFound,
+                _ => Self::Other,
+            },
+            Error::InvalidContent { .. } | Error::ContentType { .. } | Error::TooSmall { .. } => {
+                Self::InvalidContent
+            }
+            Error::Io { .. } => Self::Io,
+            Error::LogoFetch { kind, .. } => *kind,
+            _ => Self::Other,
+        }
+    }
+
+    /// Whether a fetch that failed this way may succeed if retried.
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited | Self::ServerError | Self::Timeout | Self::Connect
+        )
+    }
+}
+
+/// Whether a response status says the requested logo doesn't exist
+/// (as opposed to e.g. the request being refused).
+fn is_missing(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE
+    )
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NotFound => "not found",
+            Self::RateLimited => "rate limited",
+            Self::ServerError => "server error",
+            Self::Timeout => "timed out",
+            Self::Connect => "connection error",
+            Self::InvalidContent => "invalid content",
+            Self::Io => "I/O error",
+            Self::Other => "other error",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::*;
+
+    fn http_error(statuses: &[u16]) -> Result<LogoOutcome> {
+        Ok(LogoOutcome::HttpError {
+            attempts: statuses
+                .iter()
+                .map(|&status| {
+                    (
+                        format!("https://logos.example/{status}.svg"),
+                        StatusCode::from_u16(status).unwrap(),
+                    )
+                })
+                .collect(),
+            retry_after: None,
+        })
+    }
+
+    #[test]
+    fn missing_logos_are_not_found() {
+        for statuses in [&[404][..], &[410], &[404, 410, 404]] {
+            assert_eq!(
+                FailureKind::classify(&http_error(statuses)),
+                Some(FailureKind::NotFound),
+                "{statuses:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn refused_requests_are_other_errors() {
+        for statuses in [&[401][..], &[403], &[400], &[404, 403], &[451, 404]] {
+            assert_eq!(
+                FailureKind::classify(&http_error(statuses)),
+                Some(FailureKind::Other),
+                "{statuses:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rate_limits_and_server_errors_take_precedence() {
+        assert_eq!(
+            FailureKind::classify(&http_error(&[404, 429, 500])),
+            Some(FailureKind::RateLimited)
+        );
+        assert_eq!(
+            FailureKind::classify(&http_error(&[403, 503])),
+            Some(FailureKind::ServerError)
+        );
+    }
+
+    #[test]
+    fn error_statuses_are_classified_alike() {
+        let status = |status: u16| {
+            FailureKind::of_error(&Error::SymbolListHttp {
+                url: "https://symbols.example/list.txt".into(),
+                status: StatusCode::from_u16(status).unwrap(),
+            })
+        };
+
+        assert_eq!(status(404), FailureKind::NotFound);
+        assert_eq!(status(410), FailureKind::NotFound);
+        assert_eq!(status(403), FailureKind::Other);
+        assert_eq!(status(401), FailureKind::Other);
+        assert_eq!(status(429), FailureKind::RateLimited);
+        assert_eq!(status(502), FailureKind::ServerError);
+    }
+}
@@ -0,0 +1,1463 @@
+//! Downloading of symbol lists and logos.
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    future::Future,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use encoding_rs::Encoding;
+use flate2::{write::GzEncoder, Compression};
+use log::{debug, trace, warn};
+use reqwest::Method;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    atomic, hash,
+    rate::RateLimiter,
+    retry, svg,
+    ticker::{self, FileCase},
+    tsv, Error, Result, Symbol, Tsv,
+};
+
+/// The base URL of the logo CDN.
+pub const LOGO_BASE_URL: &str = "https://logos.stockanalysis.com";
+
+/// The location of the NYSE trading units file.
+pub const NYSE_SYMBOLS_URL: &str = "https://www.nyse.com/publicdocs/nyse/markets/nyse/NYSE_and_NYSE_MKT_Trading_Units_Daily_File.xls";
+
+/// Downloads and parses a symbol list. Delimited text is parsed
+/// as it arrives; XLS/XLSX workbooks are buffered and then parsed
+/// as a whole.
+///
+/// Text is decoded with `encoding` if given, otherwise with the
+/// charset named by the response's `Content-Type` (if any), and
+/// otherwise sniffed (see [`TsvParser::new`](tsv::TsvParser::new)).
+pub async fn fetch_symbol_list(
+    client: &reqwest::Client,
+    url: &str,
+    delimiter: char,
+    encoding: Option<&'static Encoding>,
+) -> Result<Tsv> {
+    read_symbol_list(client, url, delimiter, encoding, None, None).await
+}
+
+/// Like [`fetch_symbol_list`], but also saves the list, as
+/// downloaded, to `cache_path` (atomically, and only if it was
+/// downloaded and parsed successfully).
+pub async fn fetch_symbol_list_cached(
+    client: &reqwest::Client,
+    url: &str,
+    delimiter: char,
+    encoding: Option<&'static Encoding>,
+    cache_path: &Path,
+) -> Result<Tsv> {
+    let tmp_path = atomic::tmp_path(cache_path);
+    let io_error = |source| Error::Io {
+        path: cache_path.to_path_buf(),
+        source,
+    };
+
+    let mut cache = tokio::fs::File::create(&tmp_path).await.map_err(io_error)?;
+    let tsv = match read_symbol_list(
+        client,
+        url,
+        delimiter,
+        encoding,
+        Some((&mut cache, &tmp_path)),
+        None,
+    )
+    .await
+    {
+        Ok(tsv) => cache.flush().await.map_err(io_error).map(|()| tsv),
+        Err(e) => Err(e),
+    };
+    drop(cache);
+
+    match tsv {
+        Ok(tsv) => {
+            atomic::commit(&tmp_path, cache_path)
+                .await
+                .map_err(io_error)?;
+            trace!("cached symbol list at '{}'", cache_path.display());
+            Ok(tsv)
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            Err(e)
+        }
+    }
+}
+
+/// Rows of a symbol list, handed over as they're parsed (see
+/// [`TsvParser::take_rows`](tsv::TsvParser::take_rows)).
+type OnRows<'a> = &'a mut dyn FnMut(&Tsv, Vec<Vec<String>>) -> Result<()>;
+
+/// Downloads and parses a symbol list (see [`fetch_symbol_list`]),
+/// copying the raw response body to `cache` if given. With
+/// `on_rows`, the rows of delimited text are passed to it as they're
+/// parsed rather than collected, leaving the returned list without
+/// them.
+async fn read_symbol_list(
+    client: &reqwest::Client,
+    url: &str,
+    delimiter: char,
+    encoding: Option<&'static Encoding>,
+    mut cache: Option<(&mut tokio::fs::File, &Path)>,
+    mut on_rows: Option<OnRows<'_>>,
+) -> Result<Tsv> {
+    let started = Instant::now();
+    let http_error = |source| Error::http(url, source, started);
+
+    let mut res = client.get(url).send().await.map_err(http_error)?;
+
+    trace!("response: {:?}", res.status());
+
+    if !res.status().is_success() {
+        return Err(Error::SymbolListHttp {
+            url: res.url().to_string(),
+            status: res.status(),
+        });
+    }
+
+    let encoding = encoding.or_else(|| {
+        let content_type = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)?
+            .to_str()
+            .ok()?;
+        let charset = content_type.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"'))
+        })?;
+
+        let encoding = Encoding::for_label(charset.as_bytes());
+        trace!(
+            "response charset: {charset} ({})",
+            encoding.map_or("unknown", Encoding::name)
+        );
+        encoding
+    });
+
+    // the first chunk may be too short to tell what the list is, so
+    // it's sniffed once it holds a whole line (or all there is)
+    let mut first = Vec::new();
+    while !is_sniffable(&first) {
+        match next_chunk(&mut res, &mut cache, url, started).await? {
+            Some(chunk) => first.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+
+    if tsv::is_spreadsheet(&first) {
+        let mut content = first;
+        while let Some(chunk) = next_chunk(&mut res, &mut cache, url, started).await? {
+            content.extend_from_slice(&chunk);
+        }
+
+        return Tsv::parse(&content, delimiter, encoding);
+    }
+
+    trace!("streaming delimited text ({delimiter:?})...");
+    tsv::check_delimited(&first, delimiter)?;
+
+    let mut parser = tsv::TsvParser::with_encoding(delimiter, encoding);
+    let mut size = first.len();
+    parser.feed_bytes(&first);
+    drop(first);
+    loop {
+        if let Some(on_rows) = &mut on_rows {
+            if let Some((tsv, rows)) = parser.take_rows() {
+                on_rows(tsv, rows)?;
+            }
+        }
+
+        let Some(chunk) = next_chunk(&mut res, &mut cache, url, started).await? else {
+            break;
+        };
+        size += chunk.len();
+        parser.feed_bytes(&chunk);
+    }
+
+    trace!("symbol list size: {size} bytes");
+    parser.finish()
+}
+
+/// How much of the start of a symbol list [`is_sniffable`] waits for
+/// at most, in case its first line never ends.
+const SYMBOL_LIST_SNIFF_LIMIT: usize = 64 * 1024;
+
+/// Whether enough of the start of a symbol list has been read to
+/// tell whether it's a [spreadsheet](tsv::is_spreadsheet) or
+/// [delimited text](tsv::check_delimited): at least 8 bytes (for the
+/// spreadsheet magic numbers), including a whole first line (past
+/// any leading blank lines), or [`SYMBOL_LIST_SNIFF_LIMIT`] bytes.
+fn is_sniffable(head: &[u8]) -> bool {
+    if head.len() >= SYMBOL_LIST_SNIFF_LIMIT {
+        return true;
+    }
+
+    head.len() >= 8
+        && head
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+            .is_some_and(|start| head[start..].contains(&b'\n'))
+}
+
+/// Reads the next chunk of a symbol list response, copying it to
+/// `cache` (a file and its path) if given.
+async fn next_chunk(
+    res: &mut reqwest::Response,
+    cache: &mut Option<(&mut tokio::fs::File, &Path)>,
+    url: &str,
+    started: Instant,
+) -> Result<Option<Bytes>> {
+    let chunk = res
+        .chunk()
+        .await
+        .map_err(|source| Error::http(url, source, started))?;
+
+    if let (Some((file, path)), Some(chunk)) = (cache, &chunk) {
+        file.write_all(chunk).await.map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    }
+
+    Ok(chunk)
+}
+
+/// Reads a symbol list from disk: delimited text (with its
+/// delimiter [sniffed](tsv::sniff_delimiter)) or an XLS/XLSX
+/// workbook. Text is decoded with `encoding` if given, and
+/// otherwise sniffed.
+pub async fn read_symbol_file(path: &Path, encoding: Option<&'static Encoding>) -> Result<Tsv> {
+    let content = tokio::fs::read(path).await.map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    Tsv::parse(&content, tsv::sniff_delimiter(&content), encoding)
+}
+
+/// Where [`fetch_symbols`] gets a symbol list from.
+#[derive(Debug, Clone)]
+pub enum SymbolSource {
+    /// NYSE's daily trading units file (at [`NYSE_SYMBOLS_URL`]).
+    Nyse,
+    /// A NYSE-format symbol list (a tab-separated file or an
+    /// XLS/XLSX workbook) at the given URL.
+    Url(String),
+    /// A symbol list on disk (see [`read_symbol_file`]).
+    File(PathBuf),
+}
+
+/// Downloads (or reads) a symbol list and maps its rows to
+/// [`Symbol`]s. Rows without a ticker are skipped.
+pub async fn fetch_symbols(client: &reqwest::Client, source: SymbolSource) -> Result<Vec<Symbol>> {
+    let mut symbols = Vec::new();
+    for_each_symbol(client, source, |symbol| symbols.push(symbol)).await?;
+    Ok(symbols)
+}
+
+/// Like [`fetch_symbols`], but passes each [`Symbol`] to `on_symbol`
+/// as soon as its row has been parsed, rather than collecting them.
+/// A list downloaded as delimited text is never held whole, so
+/// memory use doesn't grow with its size; XLS/XLSX workbooks and
+/// files are still read whole first.
+pub async fn for_each_symbol(
+    client: &reqwest::Client,
+    source: SymbolSource,
+    mut on_symbol: impl FnMut(Symbol),
+) -> Result<()> {
+    let mut checked = false;
+    let mut map = |tsv: &Tsv, rows: &[Vec<String>]| -> Result<()> {
+        if !std::mem::replace(&mut checked, true) {
+            tsv.symbol_column()?;
+            tsv.check_normalized_headers()?;
+        }
+
+        rows.iter()
+            .filter_map(|row| Symbol::from_row(tsv, row))
+            .for_each(&mut on_symbol);
+        Ok(())
+    };
+
+    let url = match &source {
+        SymbolSource::Nyse => NYSE_SYMBOLS_URL,
+        SymbolSource::Url(url) => url,
+        SymbolSource::File(path) => {
+            let tsv = read_symbol_file(path, None).await?;
+            return map(&tsv, tsv.rows());
+        }
+    };
+
+    // whatever wasn't passed on as it was parsed (e.g. a workbook's
+    // rows) is left in the returned list
+    let tsv = read_symbol_list(
+        client,
+        url,
+        '\t',
+        None,
+        None,
+        Some(&mut |tsv, rows| map(tsv, &rows)),
+    )
+    .await?;
+    map(&tsv, tsv.rows())
+}
+
+/// What became of a [`fetch_logo`], [`download_logo`] or
+/// [`check_logo`] call.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LogoOutcome {
+    /// The logo was downloaded from `url` (expanded from the `source`
+    /// template) and written to `path`. `sha256` is the lowercase hex
+    /// digest of its contents, and `body_sha256` that of the logo as
+    /// downloaded (before any optimizing or compressing);
+    /// `last_modified` and `etag` are the response's headers of the
+    /// same name, if present.
+    Fetched {
+        url: String,
+        /// Where the request was redirected to, if anywhere.
+        final_url: Option<String>,
+        source: String,
+        path: PathBuf,
+        bytes: usize,
+        sha256: String,
+        body_sha256: String,
+        status: reqwest::StatusCode,
+        last_modified: Option<String>,
+        etag: Option<String>,
+        content_type: Option<String>,
+        /// How many bytes [optimizing](LogoConfig::optimize) the logo
+        /// saved, if it was optimized.
+        saved: Option<usize>,
+        /// Whether [sanitizing](LogoConfig::sanitize) the logo changed
+        /// it.
+        sanitized: bool,
+    },
+    /// The logo was downloaded (by [`download_logo`]) but not
+    /// written anywhere yet.
+    Downloaded(Logo),
+    /// The logo at `url` hasn't changed since it was last fetched
+    /// (the CDN responded `304 Not Modified` to a conditional
+    /// request; see [`Validators`]), so nothing was written.
+    NotModified { url: String },
+    /// The logo is available at `url` (expanded from the `source`
+    /// template), but wasn't downloaded (by [`check_logo`]).
+    /// `content_length` and `last_modified` are what the CDN said
+    /// about it, if anything.
+    Available {
+        url: String,
+        /// Where the request was redirected to, if anywhere.
+        final_url: Option<String>,
+        source: String,
+        status: reqwest::StatusCode,
+        content_length: Option<u64>,
+        last_modified: Option<String>,
+        content_type: Option<String>,
+    },
+    /// The logo at `url` is a known placeholder (its body's `sha256`
+    /// is one of the config's [`placeholders`](LogoConfig::placeholders)),
+    /// so it was treated as missing and not written, and no later
+    /// source had a real logo either.
+    Placeholder { url: String, sha256: String },
+    /// The ticker can't be mapped to a logo URL or file name.
+    InvalidSymbol,
+    /// Every candidate URL of every source responded with an HTTP
+    /// error status. A source that rate limits the request (with a
+    /// `429`, or a `503` with a `Retry-After` header) has its
+    /// remaining candidates skipped, and `retry_after` holds the
+    /// longest delay any source asked for, if any.
+    HttpError {
+        attempts: Vec<(String, reqwest::StatusCode)>,
+        retry_after: Option<Duration>,
+    },
+}
+
+/// The `ETag` and `Last-Modified` headers of an earlier fetch of a
+/// logo from `url`, sent back as `If-None-Match` and
+/// `If-Modified-Since` so that the CDN can skip sending an unchanged
+/// logo.
+#[derive(Debug, Clone)]
+pub struct Validators {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// A downloaded logo.
+#[derive(Debug)]
+pub struct Logo {
+    /// The URL the logo was downloaded from.
+    pub url: String,
+    /// Where the request was redirected to, if anywhere.
+    pub final_url: Option<String>,
+    /// The [source](LogoConfig::sources) template `url` was expanded
+    /// from.
+    pub source: String,
+    /// The file name the logo should be saved under.
+    pub file_name: String,
+    /// The response body, exactly as received (unless it was
+    /// [sanitized](LogoConfig::sanitize) or
+    /// [optimized](LogoConfig::optimize)).
+    pub content: Vec<u8>,
+    pub status: reqwest::StatusCode,
+    pub last_modified: Option<String>,
+    pub etag: Option<String>,
+    pub content_type: Option<String>,
+    /// The lowercase hex SHA-256 digest of the logo as downloaded
+    /// (before any optimizing).
+    pub body_sha256: String,
+    /// How many bytes optimizing the logo saved, if it was optimized.
+    pub saved: Option<usize>,
+    /// Whether sanitizing the logo changed it.
+    pub sanitized: bool,
+}
+
+impl Logo {
+    /// The [`LogoOutcome::Fetched`] for the logo, once `written`
+    /// (its content, possibly compressed) has been written to `path`.
+    pub fn fetched(&self, path: PathBuf, written: &[u8]) -> LogoOutcome {
+        LogoOutcome::Fetched {
+            url: self.url.clone(),
+            final_url: self.final_url.clone(),
+            source: self.source.clone(),
+            path,
+            bytes: written.len(),
+            sha256: hash::sha256_hex(written),
+            body_sha256: self.body_sha256.clone(),
+            status: self.status,
+            last_modified: self.last_modified.clone(),
+            etag: self.etag.clone(),
+            content_type: self.content_type.clone(),
+            saved: self.saved,
+            sanitized: self.sanitized,
+        }
+    }
+}
+
+/// How many logo responses have their HTTP version logged (to
+/// confirm which protocol the client negotiated).
+const LOGGED_VERSIONS: usize = 3;
+
+static VERSIONS_LOGGED: AtomicUsize = AtomicUsize::new(0);
+
+/// The default size cap for [`fetch_logo`] and friends, in bytes.
+pub const DEFAULT_MAX_LOGO_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The most of a logo that is read into memory to be
+/// [sanitized](LogoConfig::sanitize), [sized](LogoConfig::normalize_size)
+/// or [optimized](LogoConfig::optimize), in bytes; larger logos fail
+/// with [`Error::TooLarge`] (if `max_size` doesn't already stop them
+/// sooner) rather than being written unprocessed.
+pub const MAX_PROCESSED_LOGO_SIZE: u64 = 1024 * 1024;
+
+/// The default minimum logo size for [`fetch_logo`] and friends, in
+/// bytes; no real SVG logo is smaller.
+pub const DEFAULT_MIN_LOGO_SIZE: u64 = 50;
+
+/// The default logo source: the CDN at [`LOGO_BASE_URL`].
+pub const DEFAULT_LOGO_SOURCE: &str = "https://logos.stockanalysis.com/{symbol}.svg";
+
+/// How [`fetch_logo`] and friends request logos.
+#[derive(Debug, Clone)]
+pub struct LogoConfig {
+    /// The URL templates to try logos at, in order (see
+    /// [`check_logo_template`] for their placeholders).
+    pub sources: Vec<String>,
+    /// If set, every request waits for its turn first.
+    pub limiter: Option<Arc<RateLimiter>>,
+    /// If set, limits how many bytes per second are downloaded
+    /// (across every fetch sharing it).
+    pub bandwidth: Option<Arc<RateLimiter>>,
+    /// The largest logo to download; larger ones are abandoned
+    /// (leaving no partial file behind) with [`Error::TooLarge`].
+    /// Like the other checks of a logo's body, this moves on to the
+    /// next candidate URL or source, failing only if none has a
+    /// usable logo.
+    pub max_size: u64,
+    /// The smallest logo to accept; smaller ones (e.g. empty or
+    /// truncated bodies) fail with [`Error::TooSmall`] and aren't
+    /// written.
+    pub min_size: u64,
+    /// Whether written logos take their modification time from the
+    /// response's `Last-Modified` header (if it has a valid one).
+    pub preserve_mtime: bool,
+    /// Whether logos are checked to be SVG images (see
+    /// [`svg::looks_like_svg`]) before being written or returned;
+    /// ones that aren't fail with [`Error::InvalidContent`].
+    pub validate: bool,
+    /// Whether logos are [stripped](svg::sanitize) of scripts, event
+    /// handlers and external references before being written or
+    /// returned; ones that can't be parsed fail with
+    /// [`Error::InvalidContent`]. Like `optimize`, this means reading
+    /// each logo whole (into memory, though never more than
+    /// [`MAX_PROCESSED_LOGO_SIZE`] bytes of it, or `max_size` if
+    /// that's smaller: larger ones are abandoned as they stream in).
+    pub sanitize: bool,
+    /// If set, logos are [sized](svg::normalize_size) as squares of
+    /// this many pixels before being written or returned (which, as
+    /// with `sanitize`, means reading each logo whole).
+    pub normalize_size: Option<u32>,
+    /// Whether logos are [minified](svg::optimize) before being
+    /// written or returned. This means reading each logo whole
+    /// rather than streaming it.
+    pub optimize: bool,
+    /// What to do about successful responses whose `Content-Type`
+    /// isn't SVG's.
+    pub content_type: ContentTypeCheck,
+    /// The lowercase hex SHA-256 digests of known placeholder logos
+    /// (generic "no logo" images); logos matching one of them aren't
+    /// written, but passed over for the next source, ending in
+    /// [`LogoOutcome::Placeholder`] if none has a real logo.
+    pub placeholders: HashSet<String>,
+    /// How the names logos are written under are cased.
+    pub file_case: FileCase,
+}
+
+/// What [`fetch_logo`] and friends do about a logo response whose
+/// `Content-Type` isn't `image/svg+xml` (or a generic XML type),
+/// which usually means something changed upstream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContentTypeCheck {
+    /// Fail with [`Error::ContentType`].
+    Strict,
+    /// Log a warning, but carry on.
+    #[default]
+    Warn,
+    /// Don't check.
+    Ignore,
+}
+
+impl std::str::FromStr for ContentTypeCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "warn" => Ok(Self::Warn),
+            "ignore" => Ok(Self::Ignore),
+            _ => Err(format!(
+                "invalid content type check '{s}' (expected strict, warn or ignore)"
+            )),
+        }
+    }
+}
+
+/// The placeholders a logo URL template may contain.
+const TEMPLATE_PLACEHOLDERS: &[&str] = &["symbol", "symbol_lower", "symbol_upper", "first_letter"];
+
+/// Checks that a logo URL template only uses known placeholders, and
+/// at least one of the symbol ones:
+///
+/// - `{symbol}` / `{symbol_lower}`: one of the ticker's [candidate
+///   names](ticker::cdn_candidates), e.g. `brk.b`
+/// - `{symbol_upper}`: the same, uppercased (`BRK.B`)
+/// - `{first_letter}`: its first letter (`b`)
+pub fn check_logo_template(template: &str) -> Result<()> {
+    let error =
+        |msg: String| Error::Format(format!("invalid logo URL template '{template}': {msg}"));
+
+    let mut has_symbol = false;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            return Err(error("unclosed '{'".to_string()));
+        };
+
+        let name = &rest[start + 1..start + len];
+        if !TEMPLATE_PLACEHOLDERS.contains(&name) {
+            return Err(error(format!(
+                "unknown placeholder '{{{name}}}' (expected one of {})",
+                TEMPLATE_PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("{{{p}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+        has_symbol |= name.starts_with("symbol");
+
+        rest = &rest[start + len + 1..];
+    }
+
+    if !has_symbol {
+        return Err(error(
+            "expected a {symbol}, {symbol_lower} or {symbol_upper} placeholder".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fills in a (checked) logo URL template for the given candidate
+/// name.
+fn expand_template(template: &str, name: &str) -> String {
+    let first_letter = name.chars().next().map(String::from).unwrap_or_default();
+
+    template
+        .replace("{symbol_lower}", &name.to_lowercase())
+        .replace("{symbol_upper}", &name.to_uppercase())
+        .replace("{first_letter}", &first_letter)
+        .replace("{symbol}", name)
+}
+
+impl Default for LogoConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![DEFAULT_LOGO_SOURCE.to_string()],
+            limiter: None,
+            bandwidth: None,
+            max_size: DEFAULT_MAX_LOGO_SIZE,
+            min_size: DEFAULT_MIN_LOGO_SIZE,
+            preserve_mtime: true,
+            validate: true,
+            sanitize: true,
+            normalize_size: None,
+            optimize: false,
+            content_type: ContentTypeCheck::default(),
+            placeholders: HashSet::new(),
+            file_case: FileCase::default(),
+        }
+    }
+}
+
+/// Fetches a ticker's logo, trying each of the `config`'s
+/// [sources](LogoConfig::sources) and each of the ticker's
+/// [candidate names](ticker::cdn_candidates) in turn, and writes it
+/// to `dest` (a directory) under its [file
+/// stem](ticker::file_stem_cased), cased as `config` says.
+///
+/// Responses whose body isn't a usable logo (see
+/// [`Error::is_unusable_logo`]) or is a known placeholder are passed
+/// over for the next candidate or source, as error statuses are.
+///
+/// The logo is streamed to disk as it arrives. With `validators`
+/// (from the logo already at `dest`), the request is made
+/// conditional, and may end in [`LogoOutcome::NotModified`].
+pub async fn fetch_logo(
+    client: &reqwest::Client,
+    symbol: &str,
+    dest: &Path,
+    config: &LogoConfig,
+    validators: Option<&Validators>,
+) -> Result<LogoOutcome> {
+    request_logo(
+        client,
+        symbol,
+        config,
+        validators,
+        Method::GET,
+        move |response| async move {
+            let path = dest.join(&response.file_name);
+            response.write(path, None, config).await
+        },
+    )
+    .await
+}
+
+/// Like [`fetch_logo`], but gzips the logo (at the given level,
+/// 0-9) and writes it as `.svgz`. The size cap applies to the logo
+/// as downloaded, not once compressed.
+pub async fn fetch_logo_compressed(
+    client: &reqwest::Client,
+    symbol: &str,
+    dest: &Path,
+    level: u32,
+    config: &LogoConfig,
+    validators: Option<&Validators>,
+) -> Result<LogoOutcome> {
+    request_logo(
+        client,
+        symbol,
+        config,
+        validators,
+        Method::GET,
+        move |response| async move {
+            let path = dest.join(format!("{}z", response.file_name));
+            response.write(path, Some(level), config).await
+        },
+    )
+    .await
+}
+
+/// Like [`fetch_logo`], but returns the logo (as
+/// [`LogoOutcome::Downloaded`]) instead of writing it to disk.
+pub async fn download_logo(
+    client: &reqwest::Client,
+    symbol: &str,
+    config: &LogoConfig,
+) -> Result<LogoOutcome> {
+    request_logo(
+        client,
+        symbol,
+        config,
+        None,
+        Method::GET,
+        move |response| async move { response.download(config).await },
+    )
+    .await
+}
+
+/// Checks whether a ticker's logo is available from any of the
+/// `config`'s sources (like [`fetch_logo`], but with `HEAD` requests,
+/// or `GET` requests for just its first byte where `HEAD` isn't
+/// allowed), without downloading it.
+pub async fn check_logo(
+    client: &reqwest::Client,
+    symbol: &str,
+    config: &LogoConfig,
+) -> Result<LogoOutcome> {
+    request_logo(
+        client,
+        symbol,
+        config,
+        None,
+        Method::HEAD,
+        move |response| async move { Ok(response.available()) },
+    )
+    .await
+}
+
+/// A successful logo response whose body hasn't been read yet.
+struct LogoResponse {
+    url: String,
+    final_url: Option<String>,
+    source: String,
+    file_name: String,
+    status: reqwest::StatusCode,
+    last_modified: Option<String>,
+    etag: Option<String>,
+    content_type: Option<String>,
+    res: reqwest::Response,
+    started: Instant,
+    read: u64,
+    /// The start of the body, until it's been checked to be an SVG.
+    head: Option<Vec<u8>>,
+    /// The digest of the body read so far.
+    digest: Sha256,
+}
+
+impl LogoResponse {
+    /// The next chunk of the body, failing with [`Error::TooLarge`]
+    /// once more than the `config`'s `max_size` bytes have been read,
+    /// or (with `processed`) more than [`MAX_PROCESSED_LOGO_SIZE`],
+    /// with [`Error::TooSmall`] if the body ends before `min_size`
+    /// bytes were, or (if the `config` says to validate logos) with
+    /// [`Error::InvalidContent`] once enough has been read to tell
+    /// it isn't an SVG. With a bandwidth limiter, waits until the
+    /// chunk fits within it.
+    async fn chunk(&mut self, config: &LogoConfig, processed: bool) -> Result<Option<Bytes>> {
+        let max_size = if processed {
+            config.max_size.min(MAX_PROCESSED_LOGO_SIZE)
+        } else {
+            config.max_size
+        };
+        let too_large = || Error::TooLarge {
+            url: self.url.clone(),
+            limit: max_size,
+        };
+
+        if self.read == 0 && self.res.content_length().is_some_and(|len| len > max_size) {
+            return Err(too_large());
+        }
+
+        let chunk = self
+            .res
+            .chunk()
+            .await
+            .map_err(|source| Error::http(&self.url, source, self.started))?;
+
+        if let Some(chunk) = &chunk {
+            self.digest.update(chunk);
+            self.read += chunk.len() as u64;
+            if self.read > max_size {
+                return Err(too_large());
+            }
+
+            if let Some(bandwidth) = &config.bandwidth {
+                bandwidth.acquire_many(chunk.len() as u64).await;
+            }
+        } else if self.read < config.min_size {
+            return Err(Error::TooSmall {
+                url: self.url.clone(),
+                bytes: self.read,
+                min: config.min_size,
+            });
+        }
+
+        if config.validate {
+            if let Some(head) = &mut self.head {
+                if let Some(chunk) = &chunk {
+                    head.extend_from_slice(&chunk[..chunk.len().min(svg::SNIFF_LEN - head.len())]);
+                }
+
+                if chunk.is_none() || head.len() >= svg::SNIFF_LEN {
+                    if !svg::looks_like_svg(head) {
+                        debug!(
+                            "logo from '{}' is not an SVG; it starts with {:?}",
+                            self.url,
+                            String::from_utf8_lossy(&head[..head.len().min(100)])
+                        );
+                        return Err(Error::InvalidContent {
+                            url: self.url.clone(),
+                        });
+                    }
+                    self.head = None;
+                }
+            }
+        }
+
+        Ok(chunk)
+    }
+
+    /// The lowercase hex SHA-256 digest of the body (once it's all
+    /// been read).
+    fn body_sha256(&mut self) -> String {
+        hash::to_hex(&std::mem::take(&mut self.digest).finalize())
+    }
+
+    /// Reads the whole body, and [sanitizes](svg::sanitize),
+    /// [sizes](svg::normalize_size) and [optimizes](svg::optimize) it
+    /// if the `config` says to.
+    /// Returns it along with how many bytes optimizing saved (`None`
+    /// if it wasn't optimized) and whether sanitizing changed it.
+    ///
+    /// The body is buffered, but [`chunk`](Self::chunk) fails before
+    /// a chunk that would take it over [`MAX_PROCESSED_LOGO_SIZE`]
+    /// (or the `config`'s `max_size`) is added, and the buffer isn't
+    /// grown past that either.
+    async fn read_processed(
+        &mut self,
+        config: &LogoConfig,
+    ) -> Result<(Vec<u8>, Option<usize>, bool)> {
+        let max_size =
+            usize::try_from(config.max_size.min(MAX_PROCESSED_LOGO_SIZE)).unwrap_or(usize::MAX);
+        let mut content = Vec::new();
+        while let Some(chunk) = self.chunk(config, true).await? {
+            // grow as `Vec` would, but never past the size cap
+            let needed = content.len() + chunk.len();
+            if needed > content.capacity() {
+                let grown = (content.capacity() * 2).max(needed).min(max_size);
+                content.reserve_exact(grown - content.len());
+            }
+            content.extend_from_slice(&chunk);
+        }
+
+        let mut sanitized = false;
+        if config.sanitize {
+            let stripped = match svg::sanitize(&content) {
+                Some(Cow::Owned(stripped)) => Some(stripped),
+                Some(Cow::Borrowed(_)) => None,
+                None => {
+                    debug!("logo from '{}' couldn't be parsed to sanitize it", self.url);
+                    return Err(Error::InvalidContent {
+                        url: self.url.clone(),
+                    });
+                }
+            };
+            if let Some(stripped) = stripped {
+                trace!("stripped unsafe content from logo from '{}'", self.url);
+                content = stripped;
+                sanitized = true;
+            }
+        }
+
+        if let Some(size) = config.normalize_size {
+            let sized = match svg::normalize_size(&content, size) {
+                Some(Cow::Owned(sized)) => Some(sized),
+                Some(Cow::Borrowed(_)) => None,
+                None => {
+                    warn!(
+                        "logo from '{}' has neither a viewBox nor a width and height; \
+                         leaving its size as is",
+                        self.url
+                    );
+                    None
+                }
+            };
+            if let Some(sized) = sized {
+                content = sized;
+            }
+        }
+
+        let mut saved = None;
+        if config.optimize {
+            match svg::optimize(&content) {
+                Some(optimized) => {
+                    saved = Some(content.len().saturating_sub(optimized.len()));
+                    content = optimized;
+                }
+                None => debug!(
+                    "keeping logo from '{}' as is; it couldn't be optimized",
+                    self.url
+                ),
+            }
+        }
+
+        Ok((content, saved, sanitized))
+    }
+
+    /// What the (`HEAD`) response says about the logo, as
+    /// [`LogoOutcome::Available`].
+    fn available(self) -> LogoOutcome {
+        let headers = self.res.headers();
+        let content_length = if self.status == reqwest::StatusCode::PARTIAL_CONTENT {
+            // `Content-Range: bytes 0-0/<length>`
+            headers
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|range| range.rsplit_once('/'))
+                .and_then(|(_, length)| length.parse().ok())
+        } else {
+            headers
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|length| length.parse().ok())
+        };
+
+        LogoOutcome::Available {
+            url: self.url,
+            final_url: self.final_url,
+            source: self.source,
+            status: self.status,
+            content_length,
+            last_modified: self.last_modified,
+            content_type: self.content_type,
+        }
+    }
+
+    /// Reads (and processes) the whole body, returning it as
+    /// [`LogoOutcome::Downloaded`].
+    async fn download(mut self, config: &LogoConfig) -> Result<LogoOutcome> {
+        let (content, saved, sanitized) = self.read_processed(config).await?;
+        trace!("response size: {} bytes", self.read);
+
+        let body_sha256 = self.body_sha256();
+        if config.placeholders.contains(&body_sha256) {
+            trace!("logo from '{}' is a placeholder", self.url);
+            return Ok(LogoOutcome::Placeholder {
+                url: self.url,
+                sha256: body_sha256,
+            });
+        }
+
+        Ok(LogoOutcome::Downloaded(Logo {
+            url: self.url,
+            final_url: self.final_url,
+            source: self.source,
+            file_name: self.file_name,
+            content,
+            status: self.status,
+            last_modified: self.last_modified,
+            etag: self.etag,
+            content_type: self.content_type,
+            body_sha256,
+            saved,
+            sanitized,
+        }))
+    }
+
+    /// Streams the body to `path` (atomically), gzipping it at
+    /// `level` if given. With `sanitize`, `normalize_size` or
+    /// `optimize`, it's read whole (and processed) first instead.
+    async fn write(
+        mut self,
+        path: PathBuf,
+        level: Option<u32>,
+        config: &LogoConfig,
+    ) -> Result<LogoOutcome> {
+        let tmp_path = atomic::tmp_path(&path);
+        let io_error = |source| Error::Io {
+            path: path.clone(),
+            source,
+        };
+
+        let written = async {
+            let mut file = tokio::fs::File::create(&tmp_path).await.map_err(io_error)?;
+            let mut encoder =
+                level.map(|level| GzEncoder::new(Vec::new(), Compression::new(level)));
+            let mut sha256 = Sha256::new();
+            let mut bytes = 0;
+
+            let (mut processed, saved, sanitized) =
+                if config.sanitize || config.normalize_size.is_some() || config.optimize {
+                    let (content, saved, sanitized) = self.read_processed(config).await?;
+                    (Some(Some(Bytes::from(content))), saved, sanitized)
+                } else {
+                    (None, None, false)
+                };
+
+            loop {
+                let chunk = match &mut processed {
+                    Some(content) => content.take(),
+                    None => self.chunk(config, false).await?,
+                };
+
+                let out = match (&mut encoder, &chunk) {
+                    (Some(encoder), Some(chunk)) => {
+                        encoder.write_all(chunk).map_err(io_error)?;
+                        Bytes::from(std::mem::take(encoder.get_mut()))
+                    }
+                    (Some(_), None) => Bytes::from(
+                        encoder
+                            .take()
+                            .expect("encoder is present")
+                            .finish()
+                            .map_err(io_error)?,
+                    ),
+                    (None, Some(chunk)) => chunk.clone(),
+                    (None, None) => Bytes::new(),
+                };
+
+                sha256.update(&out);
+                bytes += out.len();
+                file.write_all(&out).await.map_err(io_error)?;
+
+                if chunk.is_none() {
+                    break;
+                }
+            }
+
+            file.flush().await.map_err(io_error)?;
+            let body_sha256 = self.body_sha256();
+
+            if let Some(last_modified) = &self.last_modified {
+                if config.preserve_mtime {
+                    set_mtime(file, last_modified).await.map_err(io_error)?;
+                }
+            }
+
+            Ok((
+                bytes,
+                hash::to_hex(&sha256.finalize()),
+                body_sha256,
+                saved,
+                sanitized,
+            ))
+        }
+        .await;
+
+        let committed = match written {
+            Ok((_, _, body_sha256, _, _)) if config.placeholders.contains(&body_sha256) => {
+                trace!("logo from '{}' is a placeholder", self.url);
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+                return Ok(LogoOutcome::Placeholder {
+                    url: self.url,
+                    sha256: body_sha256,
+                });
+            }
+            Ok(written) => atomic::commit(&tmp_path, &path)
+                .await
+                .map(|()| written)
+                .map_err(io_error),
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+                Err(e)
+            }
+        };
+        let (bytes, sha256, body_sha256, saved, sanitized) = committed?;
+
+        trace!(
+            "wrote '{}' ({} bytes downloaded, {bytes} written)",
+            path.display(),
+            self.read
+        );
+
+        Ok(LogoOutcome::Fetched {
+            url: self.url,
+            final_url: self.final_url,
+            source: self.source,
+            path,
+            bytes,
+            sha256,
+            body_sha256,
+            status: self.status,
+            last_modified: self.last_modified,
+            etag: self.etag,
+            content_type: self.content_type,
+            saved,
+            sanitized,
+        })
+    }
+}
+
+/// Sets a (fully written) file's modification time to a
+/// `Last-Modified` header's date. Dates that don't parse are
+/// ignored.
+async fn set_mtime(file: tokio::fs::File, last_modified: &str) -> std::io::Result<()> {
+    let Ok(modified) = httpdate::parse_http_date(last_modified) else {
+        trace!("ignoring unparseable Last-Modified date '{last_modified}'");
+        return Ok(());
+    };
+
+    let file = file.into_std().await;
+    tokio::task::spawn_blocking(move || file.set_modified(modified))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// A client redirect policy following up to `max` redirects (none
+/// if 0), stopping (and so returning the redirect response itself,
+/// which [`fetch_logo`] and friends count as missing) at any to a
+/// different host if `same_host_only` is set. Redirected logos are
+/// reported with their final URL.
+pub fn redirect_policy(max: usize, same_host_only: bool) -> reqwest::redirect::Policy {
+    if max == 0 {
+        return reqwest::redirect::Policy::none();
+    }
+
+    reqwest::redirect::Policy::custom(move |attempt| {
+        let cross_host = attempt
+            .previous()
+            .first()
+            .is_some_and(|first| first.host_str() != attempt.url().host_str());
+
+        if attempt.previous().len() > max {
+            attempt.error(format!("more than {max} redirects"))
+        } else if same_host_only && cross_host {
+            trace!("not following redirect to '{}'", attempt.url());
+            attempt.stop()
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Checks a logo response's `Content-Type` (see [`ContentTypeCheck`]).
+fn check_content_type(
+    url: &str,
+    content_type: Option<&str>,
+    check: ContentTypeCheck,
+) -> Result<()> {
+    if check == ContentTypeCheck::Ignore {
+        return Ok(());
+    }
+
+    let mime = content_type
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase());
+
+    match mime.as_deref() {
+        Some("image/svg+xml") => return Ok(()),
+        Some(mime @ ("text/xml" | "application/xml")) => {
+            trace!("'{url}' responded with the generic Content-Type '{mime}'");
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let e = Error::ContentType {
+        url: url.to_string(),
+        content_type: content_type.map(str::to_string),
+    };
+    if check == ContentTypeCheck::Strict {
+        return Err(e);
+    }
+
+    warn!("{e}; the logo source may have changed");
+    Ok(())
+}
+
+/// Requests a ticker's logo from each of the `config`'s sources in
+/// turn, trying each of its [candidate names](ticker::cdn_candidates)
+/// until one responds successfully with a usable logo, and returns
+/// what `accept` (which reads the response) made of it. Returns the
+/// [`LogoOutcome`] to report if none do (or if the logo wasn't
+/// modified).
+///
+/// A source failing with anything but an error status (e.g. a
+/// connection error) doesn't stop the next one being tried, and
+/// neither does `accept` failing with an [unusable
+/// logo](Error::is_unusable_logo) error or finding a
+/// [placeholder](LogoOutcome::Placeholder). If no source has the
+/// logo, the last such rejection (or failing that, error) is
+/// returned rather than an [`HttpError`](LogoOutcome::HttpError).
+///
+/// `HEAD` requests that a source rejects (with a `405` or `501`) are
+/// retried as `GET`s for just the first byte of the logo.
+async fn request_logo<F>(
+    client: &reqwest::Client,
+    symbol: &str,
+    config: &LogoConfig,
+    validators: Option<&Validators>,
+    method: Method,
+    mut accept: impl FnMut(LogoResponse) -> F,
+) -> Result<LogoOutcome>
+where
+    F: Future<Output = Result<LogoOutcome>>,
+{
+    let Some(file_stem) = ticker::file_stem_cased(symbol, config.file_case) else {
+        return Ok(LogoOutcome::InvalidSymbol);
+    };
+
+    let candidates = ticker::cdn_candidates(symbol);
+    if candidates.is_empty() {
+        return Ok(LogoOutcome::InvalidSymbol);
+    }
+
+    let mut attempts = Vec::new();
+    let mut retry_after = None;
+    let mut error = None;
+    let mut rejected = None;
+
+    'sources: for template in &config.sources {
+        for name in &candidates {
+            let url = expand_template(template, name);
+
+            if let Some(limiter) = &config.limiter {
+                limiter.acquire().await;
+            }
+
+            trace!(symbol, url = url.as_str(); "fetching {symbol} logo from '{url}'");
+
+            let mut req = client.request(method.clone(), &url);
+            if let Some(validators) = validators.filter(|v| v.url == url) {
+                if let Some(etag) = &validators.etag {
+                    req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &validators.last_modified {
+                    req = req.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            let started = Instant::now();
+            let mut sent = req.send().await;
+            if method == Method::HEAD
+                && sent.as_ref().is_ok_and(|res| {
+                    matches!(
+                        res.status(),
+                        reqwest::StatusCode::METHOD_NOT_ALLOWED
+                            | reqwest::StatusCode::NOT_IMPLEMENTED
+                    )
+                })
+            {
+                trace!("'{url}' rejected a HEAD request; requesting its first byte instead");
+                sent = client
+                    .get(&url)
+                    .header(reqwest::header::RANGE, "bytes=0-0")
+                    .send()
+                    .await;
+            }
+
+            let res = match sent {
+                Ok(res) => res,
+                Err(source) => {
+                    let e = Error::http(&url, source, started);
+                    trace!(symbol, url = url.as_str(); "failed to fetch logo for '{symbol}': {e}");
+                    error = Some(e);
+                    continue 'sources;
+                }
+            };
+            let status = res.status();
+
+            if VERSIONS_LOGGED.fetch_add(1, Ordering::Relaxed) < LOGGED_VERSIONS {
+                trace!("'{url}' responded over {:?}", res.version());
+            }
+
+            let header = |name| {
+                res.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            };
+
+            trace!("response: {status:?}");
+            let final_url = (res.url().as_str() != url).then(|| res.url().to_string());
+            if let Some(final_url) = &final_url {
+                trace!("'{url}' redirected to '{final_url}'");
+            }
+
+            if status == reqwest::StatusCode::NOT_MODIFIED {
+                return Ok(LogoOutcome::NotModified { url });
+            }
+
+            if !status.is_success() {
+                trace!(
+                    symbol,
+                    url = url.as_str(),
+                    status = status.as_u16();
+                    "failed to fetch logo for '{symbol}' (from '{url}'): {status}"
+                );
+
+                let delay = header(reqwest::header::RETRY_AFTER);
+                let rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                    || (status == reqwest::StatusCode::SERVICE_UNAVAILABLE && delay.is_some());
+
+                attempts.push((url, status));
+
+                if rate_limited {
+                    retry_after =
+                        retry_after.max(delay.as_deref().and_then(retry::parse_retry_after));
+                    continue 'sources;
+                }
+
+                continue;
+            }
+
+            let content_type = header(reqwest::header::CONTENT_TYPE);
+            if let Err(e) = check_content_type(&url, content_type.as_deref(), config.content_type) {
+                error = Some(e);
+                continue 'sources;
+            }
+
+            let response = LogoResponse {
+                url: url.clone(),
+                final_url,
+                source: template.clone(),
+                file_name: format!("{file_stem}.svg"),
+                status,
+                last_modified: header(reqwest::header::LAST_MODIFIED),
+                etag: header(reqwest::header::ETAG),
+                content_type,
+                res,
+                started,
+                read: 0,
+                head: Some(Vec::new()),
+                digest: Sha256::new(),
+            };
+
+            match accept(response).await {
+                Err(e) if e.is_unusable_logo() => {
+                    debug!("rejected logo from '{url}': {e}");
+                    rejected = Some(Err(e));
+                }
+                Ok(outcome @ LogoOutcome::Placeholder { .. }) => {
+                    debug!("rejected placeholder logo from '{url}'");
+                    rejected = Some(Ok(outcome));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    if let Some(rejected) = rejected {
+        return rejected;
+    }
+    if let Some(e) = error {
+        return Err(e);
+    }
+
+    Ok(LogoOutcome::HttpError {
+        attempts,
+        retry_after,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A logo response with the given body.
+    fn response(body: &'static str) -> LogoResponse {
+        LogoResponse {
+            url: "https://logos.example.com/aapl.svg".to_string(),
+            final_url: None,
+            source: "https://logos.example.com/{symbol}.svg".to_string(),
+            file_name: "AAPL.svg".to_string(),
+            status: reqwest::StatusCode::OK,
+            last_modified: None,
+            etag: None,
+            content_type: Some("image/svg+xml".to_string()),
+            res: reqwest::Response::from(http::Response::new(body)),
+            started: Instant::now(),
+            read: 0,
+            head: Some(Vec::new()),
+            digest: Sha256::new(),
+        }
+    }
+
+    /// Reads the whole body with [`LogoResponse::chunk`].
+    async fn read(body: &'static str, config: &LogoConfig) -> Result<Vec<u8>> {
+        let mut response = response(body);
+        let mut content = Vec::new();
+        while let Some(chunk) = response.chunk(config, false).await? {
+            content.extend_from_slice(&chunk);
+        }
+        Ok(content)
+    }
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><rect width="10" height="10"/></svg>"#;
+
+    #[tokio::test]
+    async fn svgs_are_read() {
+        let content = read(SVG, &LogoConfig::default()).await.unwrap();
+        assert_eq!(content, SVG.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn svgs_with_an_xml_declaration_are_read() {
+        const BODY: &str = concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n",
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"/>"#
+        );
+        let content = read(BODY, &LogoConfig::default()).await.unwrap();
+        assert_eq!(content, BODY.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn svgs_with_a_byte_order_mark_are_read() {
+        const BODY: &str = concat!(
+            "\u{feff}",
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"/>"#
+        );
+        let content = read(BODY, &LogoConfig::default()).await.unwrap();
+        assert_eq!(content, BODY.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn html_bodies_are_invalid_content() {
+        const BODY: &str = "<!DOCTYPE html>\n<html><head><title>404 Not Found</title></head>\
+                            <body><h1>Not Found</h1></body></html>";
+        let e = read(BODY, &LogoConfig::default()).await.unwrap_err();
+        assert!(matches!(e, Error::InvalidContent { .. }), "{e:?}");
+        assert!(e.is_unusable_logo());
+    }
+
+    #[tokio::test]
+    async fn json_bodies_are_invalid_content() {
+        const BODY: &str = r#"{"error": "logo not found", "symbol": "AAPL", "status": 404}"#;
+        let e = read(BODY, &LogoConfig::default()).await.unwrap_err();
+        assert!(matches!(e, Error::InvalidContent { .. }), "{e:?}");
+        assert!(e.is_unusable_logo());
+    }
+
+    #[tokio::test]
+    async fn invalid_content_is_read_without_validation() {
+        const BODY: &str = r#"{"error": "logo not found", "symbol": "AAPL", "status": 404}"#;
+        let config = LogoConfig {
+            validate: false,
+            ..Default::default()
+        };
+        assert_eq!(read(BODY, &config).await.unwrap(), BODY.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_size_cap_are_too_large() {
+        let config = LogoConfig {
+            max_size: 64,
+            ..Default::default()
+        };
+        let e = read(SVG, &config).await.unwrap_err();
+        assert!(matches!(e, Error::TooLarge { limit: 64, .. }), "{e:?}");
+    }
+
+    #[tokio::test]
+    async fn bodies_under_the_minimum_size_are_too_small() {
+        let e = read("<svg/>", &LogoConfig::default()).await.unwrap_err();
+        assert!(matches!(e, Error::TooSmall { bytes: 6, .. }), "{e:?}");
+    }
+}
@@ -0,0 +1,465 @@
+//! A configured, reusable logo fetcher (see [`LogoFetcher::builder`]),
+//! for fetching logos a few at a time or all at once under the same
+//! limits.
+
+use std::{collections::HashSet, path::PathBuf, sync::Arc, time::Duration};
+
+use futures_util::Stream;
+
+use crate::{
+    batch::{
+        self, Batch, FetchOptions, FetchResult, FetchSummary, LogoJob, Plan, SymbolFilter, Target,
+    },
+    breaker::CircuitBreaker,
+    concurrency::Concurrency,
+    events::EventSender,
+    rate::RateLimiter,
+    results::{FetchRecord, Outcome},
+    retry::RetryPolicy,
+    ticker::FileCase,
+    ContentTypeCheck, Error, LogoConfig, Result, Symbol,
+};
+
+/// Fetches logos with the settings it was [built](LogoFetcherBuilder)
+/// with. Every fetch it makes, by whichever method, shares its HTTP
+/// client, concurrency limit, rate limiters and circuit breaker (so
+/// once the breaker gives up, so does the fetcher).
+pub struct LogoFetcher {
+    client: reqwest::Client,
+    options: FetchOptions,
+    config: Arc<LogoConfig>,
+    concurrency: Arc<Concurrency>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl LogoFetcher {
+    /// A builder starting from [`FetchOptions::default`].
+    pub fn builder() -> LogoFetcherBuilder {
+        LogoFetcherBuilder::default()
+    }
+
+    /// Fetches the given symbols' logos, like [`fetch_logos`](crate::fetch_logos).
+    pub async fn fetch_all(&self, symbols: &[Symbol]) -> Result<FetchSummary> {
+        batch::fetch_with(self.batch(), symbols, &self.options).await
+    }
+
+    /// Starts fetching the given symbols' logos, yielding each one's
+    /// result as it finishes, like
+    /// [`fetch_logo_stream`](crate::fetch_logo_stream).
+    pub fn fetch_stream(
+        &self,
+        symbols: &[Symbol],
+    ) -> impl Stream<Item = FetchResult> + Send + Unpin + 'static {
+        let mut batch = self.batch();
+        batch::start(&mut batch, symbols, &self.options);
+        batch
+    }
+
+    /// Fetches a symbol's logo, regardless of the [filter](SymbolFilter)
+    /// (but still skipping it if it's already there, unless forced).
+    pub async fn fetch_one(&self, symbol: &Symbol) -> Result<FetchRecord> {
+        batch::create_dir(&self.options.output).await?;
+
+        let listed = symbol.ticker.trim();
+        match batch::plan(listed, &self.options) {
+            Plan::Fetch(job) => Ok(self.run(job).await),
+            Plan::Skip(record) => Ok(record),
+        }
+    }
+
+    /// Checks whether a symbol's logo is available upstream, without
+    /// downloading it (see [`check_logo`](crate::check_logo)).
+    pub async fn check(&self, symbol: &Symbol) -> FetchRecord {
+        self.run(LogoJob {
+            symbol: symbol.ticker.trim().to_string(),
+            target: Target::Check,
+            validators: None,
+        })
+        .await
+    }
+
+    /// An empty batch, for fetching logos somewhere other than the
+    /// output directory (or with conditional requests). Its fetches
+    /// count against the fetcher's limits.
+    pub fn batch(&self) -> Batch {
+        Batch::sharing(
+            self.client.clone(),
+            self.config.clone(),
+            &self.options,
+            self.concurrency.clone(),
+            self.breaker.clone(),
+        )
+    }
+
+    /// The settings the fetcher was built with.
+    pub fn options(&self) -> &FetchOptions {
+        &self.options
+    }
+
+    /// The HTTP client logos are requested with.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Whether fetching was given up on because the logo CDN kept
+    /// failing.
+    pub fn gave_up(&self) -> bool {
+        self.breaker.gave_up()
+    }
+
+    async fn run(&self, job: LogoJob) -> FetchRecord {
+        let symbol = job.symbol.clone();
+        let mut batch = self.batch();
+        batch.spawn(job);
+        match batch.next().await {
+            Some(result) => result.record(),
+            None => FetchRecord::skipped(&symbol, Outcome::NotAttempted),
+        }
+    }
+}
+
+/// Builds a [`LogoFetcher`], checking its settings make sense at
+/// [`build`](Self::build) time.
+#[derive(Debug, Clone, Default)]
+pub struct LogoFetcherBuilder {
+    client: Option<reqwest::Client>,
+    options: FetchOptions,
+    rps: Option<f64>,
+    burst: Option<u32>,
+    max_bandwidth: Option<u64>,
+}
+
+impl LogoFetcherBuilder {
+    /// The HTTP client to request logos with (by default, one with
+    /// `reqwest`'s defaults).
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// The directory logos are written to.
+    pub fn output_dir(mut self, output: impl Into<PathBuf>) -> Self {
+        self.options.output = output.into();
+        self
+    }
+
+    /// How many logos are fetched at once.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.options.jobs = jobs;
+        self
+    }
+
+    /// Whether `jobs` is lowered while the logo CDN rate limits
+    /// requests.
+    pub fn adaptive_jobs(mut self, adaptive: bool) -> Self {
+        self.options.adaptive_jobs = adaptive;
+        self
+    }
+
+    /// Whether logos already in the output directory are fetched
+    /// again.
+    pub fn force(mut self, force: bool) -> Self {
+        self.options.force = force;
+        self
+    }
+
+    /// Which symbols' logos [`LogoFetcher::fetch_all`] fetches.
+    pub fn filter(mut self, filter: SymbolFilter) -> Self {
+        self.options.filter = filter;
+        self
+    }
+
+    /// The one URL template logos are fetched from (see
+    /// [`check_logo_template`](crate::check_logo_template)).
+    pub fn url_template(self, template: impl Into<String>) -> Self {
+        self.url_templates([template])
+    }
+
+    /// The URL templates logos are fetched from, tried in turn.
+    pub fn url_templates(mut self, templates: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.options.logo.sources = templates.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// How many times a failed request is retried.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.options.retry.max_retries = retries;
+        self
+    }
+
+    /// How failed requests are retried (replacing
+    /// [`retries`](Self::retries)).
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.options.retry = retry;
+        self
+    }
+
+    /// Limits logo requests (including retries) to this many per
+    /// second.
+    pub fn rps(mut self, rps: f64) -> Self {
+        self.rps = Some(rps);
+        self
+    }
+
+    /// With [`rps`](Self::rps), how many requests may be made at once
+    /// after a quiet period (by default, one second's worth).
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+
+    /// Limits logo downloads to this many bytes per second.
+    pub fn max_bandwidth(mut self, bytes: u64) -> Self {
+        self.max_bandwidth = Some(bytes);
+        self
+    }
+
+    /// The largest logo (in bytes) to download.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.options.logo.max_size = bytes;
+        self
+    }
+
+    /// The smallest logo (in bytes) to accept.
+    pub fn min_size(mut self, bytes: u64) -> Self {
+        self.options.logo.min_size = bytes;
+        self
+    }
+
+    /// The gzip level (0-9) logos are compressed with, if at all.
+    pub fn compress(mut self, level: Option<u32>) -> Self {
+        self.options.compress = level;
+        self
+    }
+
+    /// Whether logos are written into shards.
+    pub fn sharded(mut self, sharded: bool) -> Self {
+        self.options.sharded = sharded;
+        self
+    }
+
+    /// How many fetches in a row may fail before the rest are paused
+    /// (or 0 to never pause), how long for, and how many pauses in a
+    /// row it takes to give up.
+    pub fn circuit_breaker(mut self, threshold: u32, cool_down: Duration, max_trips: u32) -> Self {
+        self.options.breaker_threshold = threshold;
+        self.options.breaker_cool_down = cool_down;
+        self.options.breaker_max_trips = max_trips;
+        self
+    }
+
+    /// Where [progress events](crate::events::FetchEvent) are sent.
+    pub fn events(mut self, events: EventSender) -> Self {
+        self.options.events = Some(events);
+        self
+    }
+
+    /// See [`LogoConfig::preserve_mtime`].
+    pub fn preserve_mtime(mut self, preserve: bool) -> Self {
+        self.options.logo.preserve_mtime = preserve;
+        self
+    }
+
+    /// See [`LogoConfig::validate`].
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.options.logo.validate = validate;
+        self
+    }
+
+    /// See [`LogoConfig::sanitize`].
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.options.logo.sanitize = sanitize;
+        self
+    }
+
+    /// See [`LogoConfig::normalize_size`].
+    pub fn normalize_size(mut self, size: Option<u32>) -> Self {
+        self.options.logo.normalize_size = size;
+        self
+    }
+
+    /// See [`LogoConfig::optimize`].
+    pub fn optimize(mut self, optimize: bool) -> Self {
+        self.options.logo.optimize = optimize;
+        self
+    }
+
+    /// See [`LogoConfig::content_type`].
+    pub fn content_type(mut self, check: ContentTypeCheck) -> Self {
+        self.options.logo.content_type = check;
+        self
+    }
+
+    /// See [`LogoConfig::placeholders`].
+    pub fn placeholders(mut self, digests: HashSet<String>) -> Self {
+        self.options.logo.placeholders = digests;
+        self
+    }
+
+    /// See [`LogoConfig::file_case`].
+    pub fn file_case(mut self, case: FileCase) -> Self {
+        self.options.logo.file_case = case;
+        self
+    }
+
+    /// The fetcher, or an [`Error::InvalidConfig`] if the settings
+    /// don't make sense together.
+    pub fn build(self) -> Result<LogoFetcher> {
+        let Self {
+            client,
+            mut options,
+            rps,
+            burst,
+            max_bandwidth,
+        } = self;
+        let invalid = |setting, reason: &str| {
+            Err(Error::InvalidConfig {
+                setting,
+                reason: reason.to_string(),
+            })
+        };
+
+        if options.jobs == 0 {
+            return invalid("jobs", "must be at least 1");
+        }
+        match rps {
+            Some(rps) if !(rps > 0.0 && rps.is_finite()) => {
+                return invalid("rps", "must be a positive number");
+            }
+            None if burst.is_some() => return invalid("burst", "requires rps"),
+            _ => {}
+        }
+        if burst == Some(0) {
+            return invalid("burst", "must be at least 1");
+        }
+        if options.logo.max_size == 0 {
+            return invalid("max_size", "must be at least 1");
+        }
+        if options.logo.min_size > options.logo.max_size {
+            return invalid("min_size", "must not be larger than max_size");
+        }
+        if options.logo.normalize_size == Some(0) {
+            return invalid("normalize_size", "must be at least 1");
+        }
+        if options.compress.is_some_and(|level| level > 9) {
+            return invalid("compress", "level must be 0-9");
+        }
+        if options.logo.sources.is_empty() {
+            return invalid("url_template", "at least one is needed");
+        }
+        for template in &options.logo.sources {
+            crate::check_logo_template(template)?;
+        }
+
+        if let Some(rps) = rps {
+            options.logo.limiter = Some(Arc::new(RateLimiter::new(
+                rps,
+                burst.unwrap_or(rps.ceil() as u32),
+            )));
+        }
+        if let Some(bytes) = max_bandwidth.filter(|&bytes| bytes > 0) {
+            // bursts of up to a tenth of a second's worth
+            let burst = u32::try_from(bytes / 10).unwrap_or(u32::MAX);
+            options.logo.bandwidth = Some(Arc::new(RateLimiter::new(bytes as f64, burst)));
+        }
+
+        let (concurrency, breaker) = options.limits();
+        Ok(LogoFetcher {
+            client: client.unwrap_or_default(),
+            config: Arc::new(options.logo.clone()),
+            options,
+            concurrency: Arc::new(concurrency),
+            breaker: Arc::new(breaker),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The setting `builder` is rejected for.
+    fn invalid_setting(builder: LogoFetcherBuilder) -> &'static str {
+        match builder.build() {
+            Err(Error::InvalidConfig { setting, .. }) => setting,
+            Err(e) => panic!("unexpected error: {e:?}"),
+            Ok(_) => panic!("built a fetcher"),
+        }
+    }
+
+    #[test]
+    fn the_defaults_are_valid() {
+        assert!(LogoFetcher::builder().build().is_ok());
+        assert!(LogoFetcher::builder()
+            .rps(2.5)
+            .burst(1)
+            .compress(Some(9))
+            .min_size(10)
+            .max_size(10)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn jobs_must_be_at_least_1() {
+        assert_eq!(invalid_setting(LogoFetcher::builder().jobs(0)), "jobs");
+    }
+
+    #[test]
+    fn rps_must_be_positive_and_finite() {
+        for rps in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                invalid_setting(LogoFetcher::builder().rps(rps)),
+                "rps",
+                "{rps}"
+            );
+        }
+    }
+
+    #[test]
+    fn burst_requires_rps() {
+        assert_eq!(invalid_setting(LogoFetcher::builder().burst(5)), "burst");
+        assert_eq!(
+            invalid_setting(LogoFetcher::builder().rps(1.0).burst(0)),
+            "burst"
+        );
+    }
+
+    #[test]
+    fn sizes_must_make_sense() {
+        assert_eq!(
+            invalid_setting(LogoFetcher::builder().max_size(0)),
+            "max_size"
+        );
+        assert_eq!(
+            invalid_setting(LogoFetcher::builder().min_size(101).max_size(100)),
+            "min_size"
+        );
+        assert_eq!(
+            invalid_setting(LogoFetcher::builder().normalize_size(Some(0))),
+            "normalize_size"
+        );
+    }
+
+    #[test]
+    fn compression_levels_go_up_to_9() {
+        assert_eq!(
+            invalid_setting(LogoFetcher::builder().compress(Some(10))),
+            "compress"
+        );
+    }
+
+    #[test]
+    fn url_templates_are_checked() {
+        assert_eq!(
+            invalid_setting(LogoFetcher::builder().url_templates(Vec::<String>::new())),
+            "url_template"
+        );
+        assert!(matches!(
+            LogoFetcher::builder()
+                .url_template("https://logos.example/{symbol.svg")
+                .build(),
+            Err(Error::Format(_))
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+//! The `index.html` gallery of fetched logos.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::{layout, missing::MissingList, Symbol};
+
+const HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Logos</title>
+<style>
+body { font-family: sans-serif; margin: 1em; }
+#filter { font-size: 1.1em; padding: 0.3em; width: 20em; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(10em, 1fr)); gap: 1em; margin-top: 1em; }
+.cell { border: 1px solid #ddd; padding: 0.5em; text-align: center; }
+.cell img { width: 100%; height: 6em; object-fit: contain; }
+.ticker { font-weight: bold; }
+.name { font-size: 0.8em; color: #555; }
+.unavailable { font-size: 0.8em; color: #999; }
+</style>
+</head>
+<body>
+<input id="filter" type="search" placeholder="Filter by ticker or name" autofocus>
+"#;
+
+const TAIL: &str = r#"<script>
+document.getElementById("filter").addEventListener("input", function () {
+  var query = this.value.toLowerCase();
+  document.querySelectorAll("[data-search]").forEach(function (el) {
+    el.style.display = el.dataset.search.indexOf(query) === -1 ? "none" : "";
+  });
+});
+</script>
+</body>
+</html>
+"#;
+
+/// Renders the gallery page for the given logo files (names
+/// relative to the output directory), labelled with the ticker and
+/// company name of the symbol they belong to (or just the file
+/// stem, for logos with no symbol). Symbols whose logo isn't among
+/// `logo_files` are listed separately as missing, noting those whose
+/// logo wasn't found upstream either (those in `missing`).
+pub fn render(symbols: &[Symbol], logo_files: &[String], missing: &MissingList) -> String {
+    let by_stem = symbols
+        .iter()
+        .filter_map(|s| Some((folded_stem(s.logo_file.as_deref()?), s)))
+        .collect::<HashMap<_, _>>();
+
+    let mut html = String::from(HEAD);
+
+    writeln!(html, "<h2>Logos ({})</h2>", logo_files.len()).unwrap();
+    html.push_str("<div class=\"grid\">\n");
+
+    for file in logo_files {
+        let symbol = by_stem.get(&folded_stem(file));
+        let ticker = symbol.map_or_else(|| layout::stem(file), |s| s.ticker.as_str());
+        let name = symbol.and_then(|s| s.company_name.as_deref()).unwrap_or("");
+
+        writeln!(
+            html,
+            "<div class=\"cell\" data-search=\"{}\"><img src=\"{}\" alt=\"{}\" loading=\"lazy\">\
+             <div class=\"ticker\">{}</div><div class=\"name\">{}</div></div>",
+            escape(&format!("{ticker} {name}").to_lowercase()),
+            escape(file),
+            escape(ticker),
+            escape(ticker),
+            escape(name),
+        )
+        .unwrap();
+    }
+
+    html.push_str("</div>\n");
+
+    let present = logo_files
+        .iter()
+        .map(|file| folded_stem(file))
+        .collect::<HashSet<_>>();
+    let mut without_logo = symbols
+        .iter()
+        .filter(|s| {
+            s.logo_file
+                .as_deref()
+                .is_none_or(|file| !present.contains(&folded_stem(file)))
+        })
+        .collect::<Vec<_>>();
+    without_logo.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+
+    writeln!(html, "<h2>Missing logos ({})</h2>", without_logo.len()).unwrap();
+    html.push_str("<ul>\n");
+    for symbol in without_logo {
+        let name = symbol.company_name.as_deref().unwrap_or("");
+        writeln!(
+            html,
+            "<li data-search=\"{}\"><span class=\"ticker\">{}</span> {}{}</li>",
+            escape(&format!("{} {name}", symbol.ticker).to_lowercase()),
+            escape(&symbol.ticker),
+            escape(name),
+            if missing.contains(&symbol.ticker) {
+                " <span class=\"unavailable\">(not found upstream)</span>"
+            } else {
+                ""
+            },
+        )
+        .unwrap();
+    }
+    html.push_str("</ul>\n");
+
+    html.push_str(TAIL);
+    html
+}
+
+/// A logo's file stem, uppercased so that logos match their symbols
+/// whatever casing they were written with.
+fn folded_stem(logo_file: &str) -> String {
+    layout::stem(logo_file).to_uppercase()
+}
+
+/// Escapes text for use in HTML content or a quoted attribute.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,48 @@
+//! Content hashing.
+
+use std::{io, path::Path, sync::Arc, thread};
+
+use sha2::{Digest, Sha256};
+
+/// The lowercase hex SHA-256 digest of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+/// The lowercase hex SHA-256 digests of the files with the given
+/// `names` in `dir` (in the same order), read and hashed in parallel
+/// on the blocking thread pool.
+pub async fn sha256_files(dir: &Path, names: Vec<String>) -> io::Result<Vec<String>> {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let per_task = names.len().div_ceil(threads).max(1);
+    let dir = Arc::new(dir.to_path_buf());
+
+    let tasks = names
+        .chunks(per_task)
+        .map(|names| {
+            let (dir, names) = (dir.clone(), names.to_vec());
+            tokio::task::spawn_blocking(move || {
+                names
+                    .iter()
+                    .map(|name| sha256_file(&dir.join(name)))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut digests = Vec::with_capacity(names.len());
+    for task in tasks {
+        digests.extend(task.await.map_err(io::Error::other)??);
+    }
+
+    Ok(digests)
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    Ok(sha256_hex(&std::fs::read(path)?))
+}
+
+/// `bytes` (e.g. a digest) as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,132 @@
+//! Where logos go in the output directory: all in it, or sharded
+//! into subdirectories by the first character of their file stem
+//! (e.g. `A/AAPL.svg`, with digits under `0-9/`).
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::atomic;
+
+/// The subdirectory a logo with the given [file
+/// stem](crate::ticker::file_stem) goes in when sharded.
+pub fn shard(file_stem: &str) -> String {
+    match file_stem.chars().next() {
+        Some(c) if c.is_ascii_digit() => "0-9".to_string(),
+        Some(c) => c.to_uppercase().collect(),
+        None => String::new(),
+    }
+}
+
+/// Whether a directory in the output directory is a [shard](shard).
+fn is_shard(name: &str) -> bool {
+    let mut chars = name.chars();
+    name == "0-9"
+        || chars
+            .next()
+            .is_some_and(|c| c.is_alphabetic() && !c.is_lowercase())
+            && chars.next().is_none()
+}
+
+/// The path of a logo, relative to the output directory (and
+/// `/`-separated).
+pub fn logo_file(file_stem: &str, extension: &str, sharded: bool) -> String {
+    if sharded {
+        format!("{}/{file_stem}.{extension}", shard(file_stem))
+    } else {
+        format!("{file_stem}.{extension}")
+    }
+}
+
+/// Where the logo with the given file stem and extension already
+/// is in the output directory: where it would be written in the
+/// given layout, or else where it would be in the other (so that
+/// switching layouts doesn't refetch every logo).
+pub fn existing(output: &Path, file_stem: &str, extension: &str, sharded: bool) -> Option<String> {
+    [sharded, !sharded]
+        .into_iter()
+        .map(|sharded| logo_file(file_stem, extension, sharded))
+        .find(|logo_file| has_logo(&output.join(logo_file)))
+}
+
+/// Whether a logo has already been written to `path`. Empty files
+/// (e.g. left by an interrupted run) don't count, so that they're
+/// fetched again.
+pub fn has_logo(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.len() > 0)
+}
+
+/// The file name of a logo given its [path](logo_file), i.e. without
+/// its shard.
+pub fn file_name(logo_file: &str) -> &str {
+    logo_file.rsplit('/').next().unwrap_or(logo_file)
+}
+
+/// The file stem of a logo given its [path](logo_file).
+pub fn stem(logo_file: &str) -> &str {
+    let name = file_name(logo_file);
+    name.strip_suffix(".svgz")
+        .or_else(|| name.strip_suffix(".svg"))
+        .unwrap_or(name)
+}
+
+/// The (sorted) [paths](logo_file) of the SVG (and SVGZ) files in
+/// the output directory, in either layout.
+pub async fn list(output: &Path) -> io::Result<Vec<String>> {
+    let mut logo_files = Vec::new();
+    for dir in dirs(output).await? {
+        let prefix = match dir.strip_prefix(output) {
+            Ok(shard) if !shard.as_os_str().is_empty() => format!("{}/", shard.display()),
+            _ => String::new(),
+        };
+
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if is_logo(&name) && !entry.file_type().await?.is_dir() {
+                logo_files.push(format!("{prefix}{name}"));
+            }
+        }
+    }
+    logo_files.sort();
+
+    Ok(logo_files)
+}
+
+/// Removes the temporary files left by interrupted writes in the
+/// output directory and its shards (see [`atomic::remove_stale`]),
+/// returning their paths.
+pub async fn remove_stale(output: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    if !tokio::fs::try_exists(output).await? {
+        return Ok(removed);
+    }
+
+    for dir in dirs(output).await? {
+        removed.extend(atomic::remove_stale(&dir).await?);
+    }
+
+    Ok(removed)
+}
+
+/// The output directory, followed by its shards.
+async fn dirs(output: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut dirs = vec![output.to_path_buf()];
+    let mut entries = tokio::fs::read_dir(output).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_shard(&name) && entry.file_type().await?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    dirs[1..].sort();
+
+    Ok(dirs)
+}
+
+/// Whether a file is a logo (rather than e.g. metadata, a PNG render
+/// or a temporary file).
+fn is_logo(name: &str) -> bool {
+    (name.ends_with(".svg") || name.ends_with(".svgz")) && !name.starts_with('.')
+}
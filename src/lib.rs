@@ -0,0 +1,55 @@
+//! Pulls exchange symbol lists and company logos.
+//!
+//! The `nyse-logos` binary is a thin CLI over this library; the
+//! symbol list parser ([`Tsv`]), the fetch functions
+//! ([`fetch_symbols`], [`for_each_symbol`], [`fetch_logos`],
+//! [`fetch_logo_stream`], [`fetch_logo`]) and the configurable
+//! [`LogoFetcher`] can be used on their own.
+
+pub mod archive;
+pub mod atomic;
+pub mod batch;
+pub mod breaker;
+pub mod cache;
+pub mod changes;
+pub mod compress;
+pub mod concurrency;
+pub mod dedup;
+mod error;
+pub mod events;
+pub mod exit;
+pub mod failed;
+pub mod failure;
+mod fetch;
+mod fetcher;
+pub mod gallery;
+pub mod hash;
+pub mod layout;
+pub mod manifest;
+pub mod missing;
+pub mod progress;
+pub mod rate;
+pub mod render;
+pub mod results;
+pub mod retry;
+pub mod shutdown;
+pub mod store;
+pub mod svg;
+pub mod symbol;
+pub mod test_symbols;
+pub mod ticker;
+pub mod tsv;
+pub mod verify;
+
+pub use batch::{fetch_logo_stream, fetch_logos, FetchOptions, FetchSummary};
+pub use error::{Error, Result};
+pub use fetch::{
+    check_logo, check_logo_template, download_logo, fetch_logo, fetch_logo_compressed,
+    fetch_symbol_list, fetch_symbol_list_cached, fetch_symbols, for_each_symbol, read_symbol_file,
+    redirect_policy, ContentTypeCheck, Logo, LogoConfig, LogoOutcome, SymbolSource, Validators,
+    DEFAULT_LOGO_SOURCE, DEFAULT_MAX_LOGO_SIZE, DEFAULT_MIN_LOGO_SIZE, LOGO_BASE_URL,
+    MAX_PROCESSED_LOGO_SIZE, NYSE_SYMBOLS_URL,
+};
+pub use fetcher::{LogoFetcher, LogoFetcherBuilder};
+pub use symbol::Symbol;
+pub use tsv::Tsv;
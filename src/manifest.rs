@@ -0,0 +1,165 @@
+//! The `manifest.toml` record of every fetched logo.
+
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{atomic, hash, Error, LogoOutcome, Result, Validators};
+
+/// The contents of `manifest.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub logo: Vec<ManifestEntry>,
+}
+
+/// A successfully fetched logo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub symbol: String,
+    /// The logo's path, relative to the output directory.
+    pub path: String,
+    pub bytes: usize,
+    /// The lowercase hex SHA-256 digest of the logo.
+    pub sha256: String,
+    /// The URL the logo was downloaded from.
+    pub url: String,
+    /// The logo source template `url` was expanded from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// When the logo was fetched (or last found to be up to date), in
+    /// seconds since the Unix epoch.
+    pub fetched_at: u64,
+    /// The path (relative to the output directory) of the identical
+    /// logo this one is a link to, if it was deduplicated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+}
+
+impl ManifestEntry {
+    /// Builds an entry for a successful fetch into `output`, or
+    /// returns `None` if the fetch didn't succeed.
+    pub fn from_outcome(symbol: &str, outcome: &LogoOutcome, output: &Path) -> Option<Self> {
+        let LogoOutcome::Fetched {
+            url,
+            source,
+            path,
+            bytes,
+            sha256,
+            last_modified,
+            etag,
+            ..
+        } = outcome
+        else {
+            return None;
+        };
+
+        let path = path.strip_prefix(output).unwrap_or(path);
+
+        Some(Self {
+            symbol: symbol.to_string(),
+            path: path.display().to_string(),
+            bytes: *bytes,
+            sha256: sha256.clone(),
+            url: url.clone(),
+            source: Some(source.clone()),
+            last_modified: last_modified.clone(),
+            etag: etag.clone(),
+            fetched_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            duplicate_of: None,
+        })
+    }
+
+    /// The validators to revalidate the logo with, or `None` if its
+    /// response had neither an `ETag` nor a `Last-Modified` header.
+    pub fn validators(&self) -> Option<Validators> {
+        if self.etag.is_none() && self.last_modified.is_none() {
+            return None;
+        }
+
+        Some(Validators {
+            url: self.url.clone(),
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        })
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`, or returns an empty one if it
+    /// doesn't exist.
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(Error::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+
+        toml::from_str(&content).map_err(|e| {
+            Error::Format(format!(
+                "failed to parse manifest at '{}': {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Adds the given entries, replacing any existing entries for
+    /// the same symbols, and sorts the manifest by symbol.
+    pub fn merge(&mut self, entries: impl IntoIterator<Item = ManifestEntry>) {
+        let mut by_symbol = self
+            .logo
+            .drain(..)
+            .map(|entry| (entry.symbol.clone(), entry))
+            .collect::<BTreeMap<_, _>>();
+
+        for entry in entries {
+            by_symbol.insert(entry.symbol.clone(), entry);
+        }
+
+        self.logo = by_symbol.into_values().collect();
+    }
+
+    /// Updates the path, size and checksum of a symbol's entry (e.g.
+    /// after its logo was compressed in place) to match `content`,
+    /// keeping the rest. Does nothing if the symbol has no entry.
+    pub fn update_file(&mut self, symbol: &str, path: &str, content: &[u8]) {
+        if let Some(entry) = self.logo.iter_mut().find(|e| e.symbol == symbol) {
+            entry.path = path.to_string();
+            entry.bytes = content.len();
+            entry.sha256 = hash::sha256_hex(content);
+        }
+    }
+
+    /// Serializes the manifest as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self)
+            .map_err(|e| Error::Format(format!("failed to serialize manifest: {e}")))
+    }
+
+    /// Writes the manifest to `path` atomically.
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let content = self.to_toml()?;
+
+        atomic::write(path, content)
+            .await
+            .map_err(|source| Error::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+}
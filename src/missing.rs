@@ -0,0 +1,75 @@
+//! The `missing.txt` list of the symbols whose logos weren't found
+//! at any source, one ticker per line.
+
+use std::{collections::BTreeSet, path::Path};
+
+use crate::{atomic, Error, Result};
+
+/// The contents of `missing.txt`.
+#[derive(Debug, Default)]
+pub struct MissingList {
+    pub symbols: BTreeSet<String>,
+}
+
+impl MissingList {
+    /// Reads the list at `path`, or returns an empty one if it
+    /// doesn't exist.
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(Error::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+
+        Ok(Self {
+            symbols: content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+
+    /// Records the outcomes of a run's fetches: drops the symbols
+    /// whose logos were found (or no longer need to be), and adds the
+    /// ones that weren't found. Other symbols (e.g. ones the run
+    /// didn't attempt, or whose fetch failed for another reason) are
+    /// kept as they were.
+    pub fn update(
+        &mut self,
+        found: impl IntoIterator<Item = String>,
+        missing: impl IntoIterator<Item = String>,
+    ) {
+        for symbol in found {
+            self.symbols.remove(&symbol);
+        }
+        self.symbols.extend(missing);
+    }
+
+    /// Whether the given symbol's logo is missing.
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.symbols.contains(symbol)
+    }
+
+    /// Writes the list to `path` atomically.
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let content = self
+            .symbols
+            .iter()
+            .map(|symbol| format!("{symbol}\n"))
+            .collect::<String>();
+
+        atomic::write(path, content)
+            .await
+            .map_err(|source| Error::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+}
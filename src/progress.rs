@@ -0,0 +1,122 @@
+//! A progress bar for the logo fetch phase, and a logger that prints
+//! above it rather than through it.
+
+use std::{
+    fmt,
+    io::IsTerminal,
+    sync::{Mutex, PoisonError},
+};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+use log::{Log, Metadata, Record};
+
+/// The bar currently shown, if any, for [`Logger`] to print above.
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Wraps a logger so that, while a [`Progress`] bar is shown, records
+/// are printed above it (with the bar hidden while they are).
+pub struct Logger<L> {
+    inner: L,
+}
+
+impl<L: Log> Logger<L> {
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+}
+
+impl<L: Log> Log for Logger<L> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+
+        let bar = ACTIVE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        match bar {
+            Some(bar) => bar.suspend(|| self.inner.log(record)),
+            None => self.inner.log(record),
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Progress through a number of logos: how many are done, how many of
+/// those failed, the throughput and the estimated time left. Does
+/// nothing when hidden (see [`Progress::new`]).
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    failed: u64,
+}
+
+impl Progress {
+    /// A progress bar through `total` logos, drawn to stderr if
+    /// `enabled` and stderr is a terminal.
+    pub fn new(total: u64, enabled: bool) -> Self {
+        if !enabled || !std::io::stderr().is_terminal() {
+            return Self::hidden();
+        }
+
+        let bar = ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stderr())
+            .with_style(
+                ProgressStyle::with_template(
+                    "{wide_bar} {pos}/{len} logos ({rate}, {msg}, ETA {eta})",
+                )
+                .expect("progress bar template is valid")
+                .with_key(
+                    "rate",
+                    |state: &ProgressState, w: &mut dyn fmt::Write| {
+                        let _ = write!(w, "{:.1}/s", state.per_sec());
+                    },
+                ),
+            )
+            .with_message("0 failed");
+        *ACTIVE.lock().unwrap_or_else(PoisonError::into_inner) = Some(bar.clone());
+
+        Self {
+            bar: Some(bar),
+            failed: 0,
+        }
+    }
+
+    /// A progress "bar" that isn't shown.
+    pub fn hidden() -> Self {
+        Self {
+            bar: None,
+            failed: 0,
+        }
+    }
+
+    /// Records that a logo is done, having failed or not.
+    pub fn inc(&mut self, failed: bool) {
+        let Some(bar) = &self.bar else {
+            return;
+        };
+
+        if failed {
+            self.failed += 1;
+            bar.set_message(format!("{} failed", self.failed));
+        }
+        bar.inc(1);
+    }
+}
+
+impl Drop for Progress {
+    /// Clears the bar, so that what's logged next isn't printed above
+    /// it.
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            *ACTIVE.lock().unwrap_or_else(PoisonError::into_inner) = None;
+            bar.finish_and_clear();
+        }
+    }
+}
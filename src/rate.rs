@@ -0,0 +1,156 @@
+//! Limiting of request rates.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// A token bucket, shared between tasks, that limits how many
+/// requests are made (or bytes downloaded) per second. Up to `burst`
+/// requests can be made at once after a quiet period; after that
+/// they're spaced out to the given rate.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+    acquired: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// A limiter allowing `per_second` requests per second, in
+    /// bursts of up to `burst` (at least 1). It starts out full.
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+
+        Self {
+            per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+            acquired: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits until a request may be made. Waiting tasks are let
+    /// through in the order they called this.
+    pub async fn acquire(&self) {
+        self.acquire_many(1).await;
+    }
+
+    /// Waits until `n` units (e.g. bytes, for a bandwidth limit) may
+    /// be used. `n` may be larger than the burst size, in which case
+    /// the wait is as long as it takes to refill that many.
+    pub async fn acquire_many(&self, n: u64) {
+        let mut bucket = self.bucket.lock().await;
+
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() * self.per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(self.burst) - n as f64;
+        bucket.updated = now;
+
+        if bucket.tokens < 0.0 {
+            let wait = Duration::from_secs_f64(-bucket.tokens / self.per_second);
+            tokio::time::sleep(wait).await;
+            bucket.tokens = 0.0;
+            bucket.updated = Instant::now();
+        }
+
+        self.acquired.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// How many requests (or units) have been let through so far.
+    pub fn acquired(&self) -> u64 {
+        self.acquired.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Acquires `n` times, returning how long it took.
+    async fn acquire_times(limiter: &RateLimiter, n: usize) -> Duration {
+        let started = Instant::now();
+        for _ in 0..n {
+            limiter.acquire().await;
+        }
+        started.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bursts_are_let_through_at_once() {
+        let limiter = RateLimiter::new(2.0, 5);
+
+        assert_eq!(acquire_times(&limiter, 5).await, Duration::ZERO);
+        assert_eq!(limiter.acquired(), 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_after_a_burst_are_spaced_out() {
+        let limiter = RateLimiter::new(2.0, 5);
+
+        acquire_times(&limiter, 5).await;
+        assert_eq!(acquire_times(&limiter, 4).await, Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_bucket_refills_after_a_quiet_period() {
+        let limiter = RateLimiter::new(10.0, 3);
+
+        acquire_times(&limiter, 3).await;
+        tokio::time::sleep(Duration::from_secs(10)).await;
+
+        // only refilled up to the burst size
+        assert_eq!(acquire_times(&limiter, 3).await, Duration::ZERO);
+        assert_eq!(acquire_times(&limiter, 1).await, Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_zero_burst_allows_one_at_a_time() {
+        let limiter = RateLimiter::new(1.0, 0);
+
+        assert_eq!(acquire_times(&limiter, 1).await, Duration::ZERO);
+        assert_eq!(acquire_times(&limiter, 2).await, Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn large_acquisitions_wait_for_the_refill() {
+        let limiter = RateLimiter::new(1000.0, 100);
+
+        let started = Instant::now();
+        limiter.acquire_many(2100).await;
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+        assert_eq!(limiter.acquired(), 2100);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_tasks_share_the_rate() {
+        let limiter = std::sync::Arc::new(RateLimiter::new(4.0, 1));
+        let started = Instant::now();
+
+        let tasks = (0..4)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { acquire_times(&limiter, 3).await })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // 12 requests at 4 per second, the first of which is free
+        assert_eq!(started.elapsed(), Duration::from_millis(2750));
+        assert_eq!(limiter.acquired(), 12);
+    }
+}
@@ -0,0 +1,108 @@
+//! Rasterizing logos to PNG.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use log::trace;
+use tokio::sync::Semaphore;
+
+use crate::{atomic, Error, Result};
+
+/// The system fonts, for logos with text in them (loaded once, as
+/// that's slow).
+static FONTS: OnceLock<Arc<resvg::usvg::fontdb::Database>> = OnceLock::new();
+
+/// Renders an SVG (or gzipped SVG) as a `size`x`size` PNG, scaled to
+/// fit (keeping its aspect ratio) and centered on a transparent
+/// canvas.
+pub fn render_png(svg: &[u8], size: u32) -> Result<Vec<u8>> {
+    let fonts = FONTS.get_or_init(|| {
+        let mut fonts = resvg::usvg::fontdb::Database::new();
+        fonts.load_system_fonts();
+        Arc::new(fonts)
+    });
+    let options = resvg::usvg::Options {
+        fontdb: fonts.clone(),
+        ..Default::default()
+    };
+
+    let tree = resvg::usvg::Tree::from_data(svg, &options)
+        .map_err(|e| Error::Render(format!("invalid SVG: {e}")))?;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size, size)
+        .ok_or_else(|| Error::Render(format!("invalid PNG size {size}")))?;
+
+    let logo = tree.size();
+    let scale = (size as f32 / logo.width()).min(size as f32 / logo.height());
+    let transform = resvg::tiny_skia::Transform::from_translate(
+        (size as f32 - logo.width() * scale) / 2.0,
+        (size as f32 - logo.height() * scale) / 2.0,
+    )
+    .pre_scale(scale, scale);
+
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    pixmap
+        .encode_png()
+        .map_err(|e| Error::Render(format!("failed to encode PNG: {e}")))
+}
+
+/// Renders logos as PNGs at a set of sizes, on the blocking thread
+/// pool, with at most `jobs` rendering at once (independently of how
+/// many are being downloaded).
+#[derive(Debug)]
+pub struct Renderer {
+    sizes: Vec<u32>,
+    semaphore: Semaphore,
+}
+
+impl Renderer {
+    pub fn new(sizes: Vec<u32>, jobs: usize) -> Self {
+        Self {
+            sizes,
+            semaphore: Semaphore::new(jobs.max(1)),
+        }
+    }
+
+    /// Renders the logo at `path` at every size, writing each next to
+    /// it as `<stem>_<size>.png`, and returns the paths written.
+    pub async fn render(&self, path: &Path, stem: &str) -> Result<Vec<PathBuf>> {
+        let _permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("semaphore is never closed");
+
+        let svg = tokio::fs::read(path).await.map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let sizes = self.sizes.clone();
+        let pngs = tokio::task::spawn_blocking(move || {
+            sizes
+                .into_iter()
+                .map(|size| Ok((size, render_png(&svg, size)?)))
+                .collect::<Result<Vec<_>>>()
+        })
+        .await
+        .map_err(|e| Error::Render(format!("rendering panicked: {e}")))??;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut written = Vec::with_capacity(pngs.len());
+        for (size, png) in pngs {
+            let png_path = dir.join(format!("{stem}_{size}.png"));
+            atomic::write(&png_path, &png)
+                .await
+                .map_err(|source| Error::Io {
+                    path: png_path.clone(),
+                    source,
+                })?;
+            trace!("rendered '{}'", png_path.display());
+            written.push(png_path);
+        }
+
+        Ok(written)
+    }
+}
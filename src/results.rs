@@ -0,0 +1,379 @@
+//! A machine-readable, line-per-symbol (JSONL) log of logo fetches.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{failure::FailureKind, Error, LogoOutcome, Result};
+
+/// What happened to a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Fetched,
+    /// An existing logo older than `--max-age` was downloaded again.
+    Refreshed,
+    /// The logo is available, but wasn't downloaded (with `--check`).
+    Available,
+    SkippedExisting,
+    UpToDate,
+    CompressedExisting,
+    SkippedInvalidSymbol,
+    /// The logo is a known placeholder, so it wasn't written.
+    Placeholder,
+    /// Fetching was given up on before reaching the symbol (see
+    /// [`CircuitBreaker`](crate::breaker::CircuitBreaker)).
+    NotAttempted,
+    HttpError,
+    RequestError,
+    TimedOut,
+    TooLarge,
+    TooSmall,
+    InvalidContent,
+    IoError,
+    Error,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Fetched => "fetched",
+            Self::Refreshed => "refreshed (stale)",
+            Self::Available => "available",
+            Self::SkippedExisting => "skipped (existing)",
+            Self::UpToDate => "up to date",
+            Self::CompressedExisting => "compressed (existing)",
+            Self::SkippedInvalidSymbol => "skipped (invalid symbol)",
+            Self::Placeholder => "placeholder",
+            Self::NotAttempted => "not attempted",
+            Self::HttpError => "HTTP error",
+            Self::RequestError => "request error",
+            Self::TimedOut => "timed out",
+            Self::TooLarge => "too large",
+            Self::TooSmall => "too small",
+            Self::InvalidContent => "invalid content",
+            Self::IoError => "I/O error",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A single line of the results log.
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchRecord {
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Where the request for `url` was redirected to, if anywhere.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    /// The logo source template the logo came from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
+    /// The logo response's `Content-Type` header, if it had one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// The logo's `Last-Modified` header, if it had one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// The logo's file name, relative to the output directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub outcome: Outcome,
+    /// Why the fetch failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureKind>,
+    /// How many times the logo was requested (including retries).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FetchRecord {
+    /// A record for a symbol that wasn't fetched at all.
+    pub fn skipped(symbol: &str, outcome: Outcome) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            url: None,
+            final_url: None,
+            source: None,
+            status: None,
+            bytes: None,
+            content_type: None,
+            last_modified: None,
+            path: None,
+            outcome,
+            failure: None,
+            attempts: None,
+            duration_ms: None,
+            error: None,
+        }
+    }
+
+    /// A record for the result of a [`fetch_logo`](crate::fetch_logo)
+    /// call that took `attempts` attempts and `duration` in total.
+    /// Failed fetches report the last URL that was tried.
+    pub fn from_result(
+        symbol: &str,
+        result: &Result<LogoOutcome>,
+        attempts: u32,
+        duration: Duration,
+    ) -> Self {
+        let mut record = Self {
+            failure: FailureKind::classify(result),
+            attempts: Some(attempts),
+            duration_ms: Some(duration.as_millis() as u64),
+            ..Self::skipped(symbol, Outcome::Error)
+        };
+
+        match result {
+            Ok(LogoOutcome::Fetched {
+                url,
+                final_url,
+                source,
+                path,
+                bytes,
+                status,
+                last_modified,
+                content_type,
+                ..
+            }) => {
+                record.outcome = Outcome::Fetched;
+                record.content_type = content_type.clone();
+                record.url = Some(url.clone());
+                record.final_url = final_url.clone();
+                record.source = Some(source.clone());
+                record.status = Some(status.as_u16());
+                record.bytes = Some(*bytes);
+                record.last_modified = last_modified.clone();
+                record.path = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned());
+            }
+            Ok(LogoOutcome::Downloaded(logo)) => {
+                record.outcome = Outcome::Fetched;
+                record.url = Some(logo.url.clone());
+                record.final_url = logo.final_url.clone();
+                record.source = Some(logo.source.clone());
+                record.status = Some(logo.status.as_u16());
+                record.bytes = Some(logo.content.len());
+                record.last_modified = logo.last_modified.clone();
+                record.content_type = logo.content_type.clone();
+                record.path = Some(logo.file_name.clone());
+            }
+            Ok(LogoOutcome::Available {
+                url,
+                final_url,
+                source,
+                status,
+                content_length,
+                last_modified,
+                content_type,
+            }) => {
+                record.outcome = Outcome::Available;
+                record.content_type = content_type.clone();
+                record.url = Some(url.clone());
+                record.final_url = final_url.clone();
+                record.source = Some(source.clone());
+                record.status = Some(status.as_u16());
+                record.bytes = content_length.map(|length| length as usize);
+                record.last_modified = last_modified.clone();
+            }
+            Ok(LogoOutcome::NotModified { url }) => {
+                record.outcome = Outcome::UpToDate;
+                record.url = Some(url.clone());
+                record.status = Some(304);
+            }
+            Ok(LogoOutcome::Placeholder { url, .. }) => {
+                record.outcome = Outcome::Placeholder;
+                record.url = Some(url.clone());
+            }
+            Ok(LogoOutcome::InvalidSymbol) => record.outcome = Outcome::SkippedInvalidSymbol,
+            Ok(LogoOutcome::HttpError { attempts, .. }) => {
+                record.outcome = Outcome::HttpError;
+                if let Some((url, status)) = attempts.last() {
+                    record.url = Some(url.clone());
+                    record.status = Some(status.as_u16());
+                }
+            }
+            Err(e) => {
+                record.error = Some(e.to_string());
+                match e {
+                    Error::Http { url, .. } => {
+                        record.outcome = Outcome::RequestError;
+                        record.url = Some(url.clone());
+                    }
+                    Error::Timeout { url, .. } => {
+                        record.outcome = Outcome::TimedOut;
+                        record.url = Some(url.clone());
+                    }
+                    Error::TooLarge { url, .. } => {
+                        record.outcome = Outcome::TooLarge;
+                        record.url = Some(url.clone());
+                    }
+                    Error::TooSmall { url, .. } => {
+                        record.outcome = Outcome::TooSmall;
+                        record.url = Some(url.clone());
+                    }
+                    Error::InvalidContent { url } => {
+                        record.outcome = Outcome::InvalidContent;
+                        record.url = Some(url.clone());
+                    }
+                    Error::ContentType { url, content_type } => {
+                        record.outcome = Outcome::InvalidContent;
+                        record.url = Some(url.clone());
+                        record.content_type = content_type.clone();
+                    }
+                    Error::Io { path, .. } => {
+                        record.outcome = Outcome::IoError;
+                        record.path = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().into_owned());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        record
+    }
+}
+
+/// How many symbols of a run had each outcome, and how many
+/// bytes of logos were fetched.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    /// The outcomes of the symbols that didn't fail.
+    pub outcomes: BTreeMap<Outcome, usize>,
+    /// Why the symbols that failed did.
+    pub failures: BTreeMap<FailureKind, usize>,
+    pub bytes: u64,
+}
+
+impl Summary {
+    /// Counts a symbol's record.
+    pub fn record(&mut self, record: &FetchRecord) {
+        match record.failure {
+            Some(kind) => *self.failures.entry(kind).or_default() += 1,
+            None => *self.outcomes.entry(record.outcome).or_default() += 1,
+        }
+
+        if matches!(record.outcome, Outcome::Fetched | Outcome::Refreshed) {
+            self.bytes += record.bytes.unwrap_or(0) as u64;
+        }
+    }
+
+    /// How many symbols failed, not counting missing logos unless
+    /// `include_missing`.
+    pub fn failed(&self, include_missing: bool) -> usize {
+        self.failures
+            .iter()
+            .filter(|(kind, _)| include_missing || **kind != FailureKind::NotFound)
+            .map(|(_, n)| n)
+            .sum()
+    }
+}
+
+/// A table of the counts, a line per outcome (or failure) that
+/// happened at all.
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .outcomes
+            .iter()
+            .map(|(outcome, n)| (outcome.to_string(), *n))
+            .chain(
+                self.failures
+                    .iter()
+                    .map(|(kind, n)| (format!("failed ({kind})"), *n)),
+            )
+            .collect::<Vec<_>>();
+        let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+
+        for (label, n) in rows {
+            writeln!(f, "{label:<width$}  {n:>6}")?;
+        }
+        write!(f, "{:<width$}  {:>6}", "bytes fetched", self.bytes)
+    }
+}
+
+/// An append-only JSONL file of [`FetchRecord`]s. Every record is
+/// flushed as soon as it's written, so an interrupted run still
+/// leaves a usable (partial) log.
+pub struct ResultsLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl ResultsLog {
+    /// Opens `path` for appending, creating it if it doesn't exist.
+    pub async fn open(path: &Path) -> Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|source| Error::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Appends a record as a single line.
+    pub async fn append(&mut self, record: &FetchRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record).expect("record serializes");
+        line.push('\n');
+
+        let io_error = |source| Error::Io {
+            path: self.path.clone(),
+            source,
+        };
+
+        self.file
+            .write_all(line.as_bytes())
+            .await
+            .map_err(io_error)?;
+        self.file.flush().await.map_err(io_error)
+    }
+
+    /// Appends a run's summary, as a last line with a single
+    /// `summary` key (which no record has).
+    pub async fn append_summary(&mut self, summary: &Summary) -> Result<()> {
+        #[derive(Serialize)]
+        struct Line<'a> {
+            summary: &'a Summary,
+        }
+
+        let mut line = serde_json::to_string(&Line { summary }).expect("summary serializes");
+        line.push('\n');
+
+        let io_error = |source| Error::Io {
+            path: self.path.clone(),
+            source,
+        };
+
+        self.file
+            .write_all(line.as_bytes())
+            .await
+            .map_err(io_error)?;
+        self.file.flush().await.map_err(io_error)
+    }
+}
@@ -0,0 +1,346 @@
+//! Retrying of transient logo fetch failures.
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::{trace, warn};
+
+use crate::{failure::FailureKind, LogoOutcome, Result};
+
+/// How many `429` responses within [`RATE_LIMIT_WINDOW`] trigger the
+/// "lower `-j`" warning.
+const RATE_LIMIT_THRESHOLD: usize = 10;
+/// See [`RATE_LIMIT_THRESHOLD`].
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(30);
+
+/// How often, and how long apart, failed requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// How many times a request is retried after its first attempt.
+    pub max_retries: u32,
+    /// The delay before the first retry; each later retry waits
+    /// twice as long as the one before.
+    pub base_delay: Duration,
+    /// The longest a `Retry-After` header is honored for; longer
+    /// delays are cut down to this.
+    pub max_retry_after: Duration,
+    rate_limits: Arc<RateLimits>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_retry_after: Duration::from_secs(60),
+            rate_limits: Arc::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before the given retry (starting at 1), with up to
+    /// 25% of random jitter added so that concurrent tasks don't
+    /// retry in lockstep.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        delay + delay.mul_f64(fastrand::f64() * 0.25)
+    }
+
+    /// Runs `fetch` until it succeeds, fails permanently, or runs
+    /// out of retries, returning its last result along with the
+    /// number of attempts made.
+    ///
+    /// Rate limited requests wait as long as the CDN's
+    /// `Retry-After` asks (up to [`max_retry_after`](Self::max_retry_after)),
+    /// falling back to the usual backoff if it didn't say. Clones of
+    /// a policy share a count of recent `429`s, and warn (once) if
+    /// there are many of them.
+    pub async fn run<F, Fut>(&self, mut fetch: F) -> (Result<LogoOutcome>, u32)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<LogoOutcome>>,
+    {
+        let fetch = || {
+            let fetched = fetch();
+            async move {
+                let result = fetched.await;
+                if let Ok(LogoOutcome::HttpError { attempts, .. }) = &result {
+                    if attempts
+                        .iter()
+                        .any(|(_, status)| *status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+                    {
+                        self.rate_limits.record();
+                    }
+                }
+                result
+            }
+        };
+
+        self.run_with(fetch, |result, attempts| {
+            if !is_transient(result) {
+                return None;
+            }
+
+            let retry_after = match result {
+                Ok(LogoOutcome::HttpError { retry_after, .. }) => *retry_after,
+                _ => None,
+            };
+            Some(retry_after.map_or_else(
+                || self.delay(attempts),
+                |delay| delay.min(self.max_retry_after),
+            ))
+        })
+        .await
+    }
+
+    /// Runs `fetch` until it runs out of retries, or `retry_delay`
+    /// (given its last result and the number of attempts so far)
+    /// returns `None`; otherwise waits as long as it returns before
+    /// trying again. Returns the last result along with the number
+    /// of attempts made.
+    pub async fn run_with<T, F, Fut>(
+        &self,
+        mut fetch: F,
+        mut retry_delay: impl FnMut(&Result<T>, u32) -> Option<Duration>,
+    ) -> (Result<T>, u32)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempts = 0;
+
+        loop {
+            let result = fetch().await;
+            attempts += 1;
+
+            if attempts > self.max_retries {
+                return (result, attempts);
+            }
+
+            let Some(delay) = retry_delay(&result, attempts) else {
+                return (result, attempts);
+            };
+
+            trace!("transient failure (attempt {attempts}); retrying in {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Recent `429` responses, shared between the clones of a
+/// [`RetryPolicy`].
+#[derive(Debug, Default)]
+struct RateLimits {
+    seen: Mutex<VecDeque<Instant>>,
+    warned: AtomicBool,
+}
+
+impl RateLimits {
+    /// Records a `429`, returning whether this is the one that
+    /// triggered the warning.
+    fn record(&self) -> bool {
+        let now = Instant::now();
+
+        let count = {
+            let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+            seen.push_back(now);
+            while seen
+                .front()
+                .is_some_and(|t| now.duration_since(*t) > RATE_LIMIT_WINDOW)
+            {
+                seen.pop_front();
+            }
+            seen.len()
+        };
+
+        let warn = count > RATE_LIMIT_THRESHOLD && !self.warned.swap(true, Ordering::Relaxed);
+        if warn {
+            warn!(
+                "the logo CDN is rate limiting requests ({count} '429 Too Many Requests' \
+                 responses in the last {}s); consider lowering -j",
+                RATE_LIMIT_WINDOW.as_secs()
+            );
+        }
+        warn
+    }
+}
+
+/// Whether a fetch failed in a way that may succeed if retried (see
+/// [`FailureKind::is_transient`]).
+pub fn is_transient(result: &Result<LogoOutcome>) -> bool {
+    FailureKind::classify(result).is_some_and(FailureKind::is_transient)
+}
+
+/// Whether a fetch was rate limited: any of its sources responded
+/// `429` or `503`.
+pub fn is_rate_limited(result: &Result<LogoOutcome>) -> bool {
+    matches!(result, Ok(LogoOutcome::HttpError { attempts, .. })
+    if attempts.iter().any(|(_, status)| {
+        matches!(
+            *status,
+            reqwest::StatusCode::TOO_MANY_REQUESTS | reqwest::StatusCode::SERVICE_UNAVAILABLE
+        )
+    }))
+}
+
+/// Parses a `Retry-After` header value, either a number of seconds
+/// or an HTTP date (a date in the past gives a zero delay).
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+
+    use reqwest::StatusCode;
+
+    use super::*;
+
+    fn rate_limited(retry_after: Option<Duration>) -> Result<LogoOutcome> {
+        Ok(LogoOutcome::HttpError {
+            attempts: vec![(
+                "https://logos.example/abc.svg".into(),
+                StatusCode::TOO_MANY_REQUESTS,
+            )],
+            retry_after,
+        })
+    }
+
+    #[test]
+    fn retry_after_seconds_are_parsed() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-1"), None);
+    }
+
+    #[test]
+    fn retry_after_dates_are_parsed() {
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(90));
+        let delay = parse_retry_after(&date).unwrap();
+        // HTTP dates only have whole seconds
+        assert!(
+            delay > Duration::from_secs(88) && delay <= Duration::from_secs(90),
+            "{delay:?}"
+        );
+    }
+
+    #[test]
+    fn retry_after_dates_in_the_past_give_no_delay() {
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_after_is_honored_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            max_retry_after: Duration::from_secs(60),
+            ..RetryPolicy::default()
+        };
+
+        let started = tokio::time::Instant::now();
+        let (result, attempts) = policy
+            .run(|| async { rate_limited(Some(Duration::from_secs(5))) })
+            .await;
+        assert!(is_rate_limited(&result));
+        assert_eq!(attempts, 3);
+        assert_eq!(started.elapsed(), Duration::from_secs(10));
+
+        let started = tokio::time::Instant::now();
+        let (result, _) = policy
+            .run(|| async { rate_limited(Some(Duration::from_secs(3600))) })
+            .await;
+        assert!(is_rate_limited(&result));
+        assert_eq!(started.elapsed(), Duration::from_secs(120));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limits_without_retry_after_back_off() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_secs(1),
+            ..RetryPolicy::default()
+        };
+
+        let started = tokio::time::Instant::now();
+        let (result, _) = policy.run(|| async { rate_limited(None) }).await;
+        assert!(is_rate_limited(&result));
+        // 1s then 2s, each with up to 25% jitter
+        let elapsed = started.elapsed();
+        assert!(
+            elapsed >= Duration::from_secs(3) && elapsed <= Duration::from_millis(3750),
+            "{elapsed:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanent_failures_are_not_retried() {
+        let calls = AtomicU32::new(0);
+        let (_, attempts) = RetryPolicy::default()
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(LogoOutcome::HttpError {
+                    attempts: vec![(
+                        "https://logos.example/abc.svg".into(),
+                        StatusCode::NOT_FOUND,
+                    )],
+                    retry_after: None,
+                })
+            })
+            .await;
+
+        assert_eq!(attempts, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn clones_share_rate_limit_counts() {
+        let policy = RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        };
+
+        for _ in 0..RATE_LIMIT_THRESHOLD {
+            let (result, _) = policy.clone().run(|| async { rate_limited(None) }).await;
+            assert!(is_rate_limited(&result));
+        }
+        assert!(!policy.rate_limits.warned.load(Ordering::Relaxed));
+
+        let (result, _) = policy.clone().run(|| async { rate_limited(None) }).await;
+        assert!(is_rate_limited(&result));
+        assert!(policy.rate_limits.warned.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn the_rate_limit_warning_is_given_once() {
+        let rate_limits = RateLimits::default();
+        let warnings = (0..RATE_LIMIT_THRESHOLD * 3)
+            .filter(|_| rate_limits.record())
+            .count();
+        assert_eq!(warnings, 1);
+    }
+}
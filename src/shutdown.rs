@@ -0,0 +1,78 @@
+//! Stopping a run early on Ctrl+C (or SIGTERM).
+
+use log::warn;
+use tokio::sync::watch;
+
+/// Whether a shutdown was requested, shared between the tasks that
+/// should stop early because of one.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    requested: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    /// Listens for Ctrl+C (and, on unix, SIGTERM) in the background:
+    /// the first requests a shutdown, and the second exits the
+    /// process immediately with `exit_code`.
+    pub fn listen(exit_code: i32) -> Self {
+        let (tx, requested) = watch::channel(false);
+
+        tokio::spawn(async move {
+            loop {
+                if signal().await.is_err() {
+                    // no signals can be listened for, so none will come
+                    return;
+                }
+
+                if tx.send_replace(true) {
+                    std::process::exit(exit_code);
+                }
+                warn!(
+                    "interrupted; stopping after cleaning up (press Ctrl+C again to exit \
+                     immediately)"
+                );
+            }
+        });
+
+        Self { requested }
+    }
+
+    /// A shutdown that's never requested, for work that should run
+    /// to the end whatever signals come in.
+    pub fn never() -> Self {
+        let (_, requested) = watch::channel(false);
+        Self { requested }
+    }
+
+    /// Whether a shutdown was requested.
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Waits until a shutdown is requested.
+    pub async fn requested(&self) {
+        let mut requested = self.requested.clone();
+        if requested.wait_for(|requested| *requested).await.is_err() {
+            // the listener is gone, so no shutdown will be requested
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Waits for Ctrl+C or SIGTERM.
+#[cfg(unix)]
+async fn signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+/// Waits for Ctrl+C.
+#[cfg(not(unix))]
+async fn signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
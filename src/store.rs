@@ -0,0 +1,157 @@
+//! SQLite storage of the symbol table and logo fetch results.
+
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::trace;
+use rusqlite::{params, Connection};
+
+use crate::{LogoOutcome, Result, Symbol};
+
+/// How many logo rows are written per transaction.
+const BATCH_SIZE: usize = 256;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS symbols (
+        ticker TEXT PRIMARY KEY NOT NULL,
+        company_name TEXT,
+        issue_type TEXT,
+        exchange TEXT,
+        logo_file TEXT,
+        raw_columns TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS logos (
+        ticker TEXT PRIMARY KEY NOT NULL,
+        path TEXT,
+        bytes INTEGER,
+        sha256 TEXT,
+        fetched_at INTEGER NOT NULL,
+        http_status INTEGER
+    );
+";
+
+/// A SQLite database holding a `symbols` table and a `logos`
+/// table. Rows are keyed on the ticker, so re-recording a symbol
+/// or logo replaces the earlier row.
+///
+/// Logo rows are written in batched transactions; call
+/// [`Store::finish`] to commit the last batch.
+pub struct Store {
+    conn: Connection,
+    pending: usize,
+}
+
+impl Store {
+    /// Opens (or creates) the database at `path`, creating the
+    /// tables if they don't exist yet.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self { conn, pending: 0 })
+    }
+
+    /// Upserts the given symbols (in a single transaction).
+    /// Columns without a dedicated field are stored as a JSON
+    /// object in `raw_columns`.
+    pub fn record_symbols(&mut self, symbols: &[Symbol]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO symbols
+                    (ticker, company_name, issue_type, exchange, logo_file, raw_columns)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+
+            for symbol in symbols {
+                let raw_columns =
+                    serde_json::to_string(&symbol.extra).expect("string map serializes");
+                stmt.execute(params![
+                    symbol.ticker,
+                    symbol.company_name,
+                    symbol.issue_type,
+                    symbol.exchange,
+                    symbol.logo_file,
+                    raw_columns,
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        trace!("recorded {} symbols", symbols.len());
+
+        Ok(())
+    }
+
+    /// Upserts the result of a logo fetch. Symbols that have no
+    /// logo URL (and logos that were downloaded but not written
+    /// anywhere) are not recorded; failed fetches are recorded with
+    /// the status of the last attempted URL and no path.
+    pub fn record_logo(&mut self, ticker: &str, outcome: &LogoOutcome) -> Result<()> {
+        let fetched_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+
+        let (path, bytes, sha256, status) = match outcome {
+            LogoOutcome::Fetched {
+                path,
+                bytes,
+                sha256,
+                status,
+                ..
+            } => (
+                Some(path.display().to_string()),
+                Some(*bytes as i64),
+                Some(sha256.as_str()),
+                Some(status.as_u16()),
+            ),
+            LogoOutcome::HttpError { attempts, .. } => (
+                None,
+                None,
+                None,
+                attempts.last().map(|(_, status)| status.as_u16()),
+            ),
+            LogoOutcome::Downloaded(_)
+            | LogoOutcome::NotModified { .. }
+            | LogoOutcome::Available { .. }
+            | LogoOutcome::Placeholder { .. }
+            | LogoOutcome::InvalidSymbol => return Ok(()),
+        };
+
+        if self.pending == 0 {
+            self.conn.execute_batch("BEGIN")?;
+        }
+
+        self.conn.execute(
+            "INSERT OR REPLACE INTO logos
+                (ticker, path, bytes, sha256, fetched_at, http_status)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![ticker, path, bytes, sha256, fetched_at, status],
+        )?;
+
+        self.pending += 1;
+        if self.pending >= BATCH_SIZE {
+            self.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Commits any logo rows that are still pending.
+    pub fn finish(mut self) -> Result<()> {
+        self.commit()
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        if self.pending > 0 {
+            self.conn.execute_batch("COMMIT")?;
+            trace!("committed {} logo rows", self.pending);
+            self.pending = 0;
+        }
+
+        Ok(())
+    }
+}
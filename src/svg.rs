@@ -0,0 +1,755 @@
+//! Checking that downloaded logos are SVG images, and sanitizing and
+//! minifying them.
+
+use std::borrow::Cow;
+
+use quick_xml::{
+    events::{attributes::Attribute, BytesStart, Event},
+    Reader, Writer, XmlVersion,
+};
+
+/// How much of the start of a logo [`looks_like_svg`] is given.
+pub const SNIFF_LEN: usize = 4096;
+
+/// Whether the start of a logo looks like an SVG image (rather than,
+/// say, an HTML or JSON error page served with a 200): after an
+/// optional byte order mark, and any whitespace, XML declaration,
+/// comments and doctype, its root element must be `<svg>`.
+pub fn looks_like_svg(head: &[u8]) -> bool {
+    let mut rest = head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head);
+
+    loop {
+        rest = rest.trim_ascii_start();
+
+        let end: &[u8] = if rest.starts_with(b"<?") {
+            b"?>"
+        } else if rest.starts_with(b"<!--") {
+            b"-->"
+        } else if rest.starts_with(b"<!") {
+            // a doctype, possibly with an internal subset
+            match rest.iter().position(|&c| c == b'[' || c == b'>') {
+                Some(i) if rest[i] == b'[' => b"]>",
+                _ => b">",
+            }
+        } else {
+            break;
+        };
+
+        let Some(i) = rest.windows(end.len()).position(|w| w == end) else {
+            return false;
+        };
+        rest = &rest[i + end.len()..];
+    }
+
+    rest.strip_prefix(b"<svg").is_some_and(|after| {
+        after
+            .first()
+            .is_none_or(|&c| c.is_ascii_whitespace() || c == b'>' || c == b'/')
+    })
+}
+
+/// The elements [`optimize`] removes (along with their contents).
+const REMOVED_ELEMENTS: &[&[u8]] = &[b"metadata", b"title", b"desc"];
+
+/// Minifies an SVG: drops its XML declaration, comments, `<metadata>`,
+/// `<title>` and `<desc>` elements, and whitespace between tags
+/// (except within `<text>` elements, where it's significant).
+/// Everything else is kept byte for byte, so the result only depends
+/// on the input.
+///
+/// Returns `None` if the SVG isn't well-formed XML, or (in case of
+/// doubt) if the result somehow isn't; either way, the original
+/// should be kept.
+pub fn optimize(svg: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = Reader::from_reader(svg);
+    let mut writer = Writer::new(Vec::with_capacity(svg.len()));
+    // how deep into a removed element (or a `<text>` element) we are
+    let (mut removing, mut in_text) = (0usize, 0usize);
+
+    loop {
+        let event = reader.read_event().ok()?;
+
+        if removing > 0 {
+            match event {
+                Event::Start(_) => removing += 1,
+                Event::End(_) => removing -= 1,
+                Event::Eof => return None,
+                _ => {}
+            }
+            continue;
+        }
+
+        match &event {
+            Event::Eof => break,
+            Event::Decl(_) | Event::Comment(_) => continue,
+            Event::Start(e) if REMOVED_ELEMENTS.contains(&e.local_name().as_ref()) => {
+                removing = 1;
+                continue;
+            }
+            Event::Empty(e) if REMOVED_ELEMENTS.contains(&e.local_name().as_ref()) => continue,
+            Event::Start(e) if e.local_name().as_ref() == b"text" => in_text += 1,
+            Event::End(e) if e.local_name().as_ref() == b"text" => {
+                in_text = in_text.saturating_sub(1);
+            }
+            Event::Text(text) if in_text == 0 && text.iter().all(u8::is_ascii_whitespace) => {
+                continue;
+            }
+            _ => {}
+        }
+
+        writer.write_event(event).ok()?;
+    }
+
+    let optimized = writer.into_inner();
+    (is_well_formed(&optimized) && looks_like_svg(&optimized)).then_some(optimized)
+}
+
+/// The elements [`sanitize`] removes (along with their contents).
+const UNSAFE_ELEMENTS: &[&[u8]] = &[b"script", b"foreignObject"];
+
+/// Strips an SVG of what could run code or load anything from
+/// elsewhere when it's embedded in a page: `<script>` and
+/// `<foreignObject>` elements, animations (`<set>`, `<animate>`)
+/// that rewrite an `href`, `<style>` elements that `@import` or
+/// reference anything outside the document, event handler (`on*`)
+/// attributes, `href`s (or `xlink:href`s) other than references
+/// within the document (`#id`) and `data:image/` URIs, animation
+/// values (`to`, `from` and `values`) with `javascript:` URIs, and
+/// attributes (including `style`) with CSS `url()`s pointing outside
+/// the document.
+///
+/// Returns the SVG as is (borrowed) if there was nothing to strip,
+/// or `None` if it isn't well-formed XML.
+pub fn sanitize(svg: &[u8]) -> Option<Cow<'_, [u8]>> {
+    if !is_well_formed(svg) {
+        return None;
+    }
+
+    let mut reader = Reader::from_reader(svg);
+    let mut writer = Writer::new(Vec::with_capacity(svg.len()));
+    // how deep into a removed element we are
+    let mut removing = 0usize;
+    let mut changed = false;
+
+    loop {
+        let event = reader.read_event().ok()?;
+
+        if removing > 0 {
+            match event {
+                Event::Start(_) => removing += 1,
+                Event::End(_) => removing -= 1,
+                Event::Eof => return None,
+                _ => {}
+            }
+            continue;
+        }
+
+        let event = match event {
+            Event::Eof => break,
+            Event::Start(e) if is_style_element(&e) => {
+                let mut events = vec![Event::Start(e)];
+                let safe = loop {
+                    match reader.read_event().ok()? {
+                        Event::Eof => return None,
+                        Event::End(end) if end.local_name().as_ref() == b"style" => {
+                            events.push(Event::End(end));
+                            break is_safe_style(&events);
+                        }
+                        event => events.push(event),
+                    }
+                };
+
+                if safe {
+                    for event in events {
+                        writer.write_event(event).ok()?;
+                    }
+                } else {
+                    changed = true;
+                }
+                continue;
+            }
+            Event::Start(e) if is_unsafe_element(&e) => {
+                removing = 1;
+                changed = true;
+                continue;
+            }
+            Event::Empty(e) if is_unsafe_element(&e) => {
+                changed = true;
+                continue;
+            }
+            Event::Start(e) => match strip_attributes(&e)? {
+                Some(stripped) => {
+                    changed = true;
+                    Event::Start(stripped)
+                }
+                None => Event::Start(e),
+            },
+            Event::Empty(e) => match strip_attributes(&e)? {
+                Some(stripped) => {
+                    changed = true;
+                    Event::Empty(stripped)
+                }
+                None => Event::Empty(e),
+            },
+            event => event,
+        };
+
+        writer.write_event(event).ok()?;
+    }
+
+    Some(if changed {
+        Cow::Owned(writer.into_inner())
+    } else {
+        Cow::Borrowed(svg)
+    })
+}
+
+/// The animation elements that [`sanitize`] removes if they target
+/// an `href`, since they could swap it for anything.
+const ANIMATION_ELEMENTS: &[&[u8]] = &[b"set", b"animate"];
+
+fn is_unsafe_element(e: &BytesStart<'_>) -> bool {
+    let name = e.local_name();
+    let is = |names: &[&[u8]]| {
+        names
+            .iter()
+            .any(|unsafe_name| name.as_ref().eq_ignore_ascii_case(unsafe_name))
+    };
+
+    is(UNSAFE_ELEMENTS) || (is(ANIMATION_ELEMENTS) && animates_href(e))
+}
+
+/// Whether an animation element's `attributeName` is an `href` (or
+/// can't be read).
+fn animates_href(e: &BytesStart<'_>) -> bool {
+    e.attributes().any(|a| {
+        let Ok(a) = a else {
+            return true;
+        };
+        if a.key.local_name().as_ref() != b"attributeName" {
+            return false;
+        }
+
+        a.normalized_value(XmlVersion::Implicit1_0)
+            .map_or(true, |value| {
+                let value = value.trim();
+                let name = value.rsplit(':').next().unwrap_or(value);
+                name.eq_ignore_ascii_case("href")
+            })
+    })
+}
+
+fn is_style_element(e: &BytesStart<'_>) -> bool {
+    e.local_name().as_ref() == b"style"
+}
+
+/// Whether a `<style>` element's events (from its start to its end)
+/// are free of [external references](is_unsafe_css). Anything but
+/// plain text and CDATA inside it (e.g. an entity reference, which
+/// could spell out an `@import`) counts as unsafe.
+fn is_safe_style(events: &[Event<'_>]) -> bool {
+    let mut css = Vec::new();
+    for event in &events[1..events.len() - 1] {
+        match event {
+            Event::Text(text) => css.extend_from_slice(text),
+            Event::CData(cdata) => css.extend_from_slice(cdata),
+            Event::Comment(_) => {}
+            _ => return false,
+        }
+    }
+
+    !is_unsafe_css(&String::from_utf8_lossy(&css))
+}
+
+/// Whether CSS (a stylesheet, or a `style` or other attribute's
+/// value) could load anything from elsewhere: it `@import`s
+/// something, has a `url()` other than a reference within the
+/// document or a `data:image/` URI, or has a backslash escape (which
+/// could spell out either).
+fn is_unsafe_css(css: &str) -> bool {
+    let css = css.to_ascii_lowercase();
+    if css.contains("@import") || css.contains('\\') {
+        return true;
+    }
+
+    css.match_indices("url(").any(|(i, url)| {
+        let target = css[i + url.len()..].trim_start();
+        let target = target.strip_prefix(['"', '\'']).unwrap_or(target);
+        !(target.starts_with('#') || target.starts_with("data:image/"))
+    })
+}
+
+/// Whether a value is a `javascript:` URI, allowing for the
+/// whitespace and control characters browsers ignore in URIs.
+fn is_javascript_uri(value: &str) -> bool {
+    let value = value
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    value.contains("javascript:")
+}
+
+/// An element without its unsafe attributes (see [`sanitize`]), or
+/// `None` if it has none; fails if its attributes don't parse.
+fn strip_attributes(e: &BytesStart<'_>) -> Option<Option<BytesStart<'static>>> {
+    let attributes = e.attributes().collect::<Result<Vec<_>, _>>().ok()?;
+    if !attributes.iter().any(is_unsafe_attribute) {
+        return Some(None);
+    }
+
+    let mut stripped = e.to_owned();
+    stripped.clear_attributes();
+    stripped.extend_attributes(attributes.into_iter().filter(|a| !is_unsafe_attribute(a)));
+    Some(Some(stripped))
+}
+
+fn is_unsafe_attribute(attribute: &Attribute<'_>) -> bool {
+    let name = attribute.key.local_name();
+    let name = name.as_ref();
+
+    if name.len() > 2 && name[..2].eq_ignore_ascii_case(b"on") {
+        return true;
+    }
+
+    // a value that can't be read can't be vetted either
+    let Ok(value) = attribute.normalized_value(XmlVersion::Implicit1_0) else {
+        return true;
+    };
+
+    if name.eq_ignore_ascii_case(b"href") {
+        let value = value.trim().to_ascii_lowercase();
+        return !(value.starts_with('#') || value.starts_with("data:image/"));
+    }
+    if [b"to".as_slice(), b"from", b"values"]
+        .iter()
+        .any(|n| name.eq_ignore_ascii_case(n))
+        && is_javascript_uri(&value)
+    {
+        return true;
+    }
+
+    is_unsafe_css(&value)
+}
+
+/// Sizes an SVG as a `size`x`size` square: sets its root element's
+/// `width` and `height` to `size`, and its `viewBox` to the smallest
+/// square containing the original one (or, without one, the
+/// original `width` and `height`), centered, so that the logo keeps
+/// its aspect ratio. Sizing an SVG that's already sized this way
+/// leaves it as is (borrowed).
+///
+/// Returns `None` if the SVG has neither a `viewBox` nor a `width`
+/// and `height` (in pixels) to size it by, or isn't well-formed.
+pub fn normalize_size(svg: &[u8], size: u32) -> Option<Cow<'_, [u8]>> {
+    if !is_well_formed(svg) {
+        return None;
+    }
+
+    let mut reader = Reader::from_reader(svg);
+    let mut writer = Writer::new(Vec::with_capacity(svg.len() + 64));
+    let mut sized = false;
+
+    loop {
+        let event = match reader.read_event().ok()? {
+            Event::Eof => break,
+            Event::Start(e) if !sized => {
+                sized = true;
+                Event::Start(match square_root_element(&e, size)? {
+                    Some(squared) => squared,
+                    None => return Some(Cow::Borrowed(svg)),
+                })
+            }
+            Event::Empty(e) if !sized => {
+                sized = true;
+                Event::Empty(match square_root_element(&e, size)? {
+                    Some(squared) => squared,
+                    None => return Some(Cow::Borrowed(svg)),
+                })
+            }
+            event => event,
+        };
+
+        writer.write_event(event).ok()?;
+    }
+
+    Some(Cow::Owned(writer.into_inner()))
+}
+
+/// The root element sized as a square (see [`normalize_size`]), or
+/// `Some(None)` if it already is.
+fn square_root_element(e: &BytesStart<'_>, size: u32) -> Option<Option<BytesStart<'static>>> {
+    let attributes = e.attributes().collect::<Result<Vec<_>, _>>().ok()?;
+    let value = |name: &[u8]| {
+        attributes
+            .iter()
+            .find(|a| a.key.as_ref() == name)
+            .and_then(|a| a.normalized_value(XmlVersion::Implicit1_0).ok())
+    };
+
+    let (min_x, min_y, width, height) = match value(b"viewBox").and_then(|v| parse_view_box(&v)) {
+        Some(view_box) => view_box,
+        None => (
+            0.0,
+            0.0,
+            parse_length(&value(b"width")?)?,
+            parse_length(&value(b"height")?)?,
+        ),
+    };
+
+    let side = width.max(height);
+    let view_box = format!(
+        "{} {} {side} {side}",
+        min_x - (side - width) / 2.0 + 0.0,
+        min_y - (side - height) / 2.0 + 0.0,
+    );
+    let size = size.to_string();
+
+    if value(b"width").as_deref() == Some(size.as_str())
+        && value(b"height").as_deref() == Some(size.as_str())
+        && value(b"viewBox").as_deref() == Some(view_box.as_str())
+    {
+        return Some(None);
+    }
+
+    let mut squared = e.to_owned();
+    squared.clear_attributes();
+    squared.extend_attributes(
+        attributes
+            .into_iter()
+            .filter(|a| !matches!(a.key.as_ref(), b"width" | b"height" | b"viewBox")),
+    );
+    squared.push_attribute(("width", size.as_str()));
+    squared.push_attribute(("height", size.as_str()));
+    squared.push_attribute(("viewBox", view_box.as_str()));
+    Some(Some(squared))
+}
+
+/// Parses a `viewBox` (`min-x min-y width height`, separated by
+/// whitespace and/or commas) with a positive width and height.
+fn parse_view_box(value: &str) -> Option<(f64, f64, f64, f64)> {
+    let numbers = value
+        .split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<_>>>()?;
+
+    match numbers[..] {
+        [min_x, min_y, width, height] if width > 0.0 && height > 0.0 => {
+            Some((min_x, min_y, width, height))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a positive `width` or `height` in pixels (with or without
+/// a `px` unit).
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    value
+        .strip_suffix("px")
+        .unwrap_or(value)
+        .trim_end()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n > 0.0)
+}
+
+/// Whether a (whole) document is an SVG image: it
+/// [looks like one](looks_like_svg), and is well-formed XML (so, for
+/// one, it isn't truncated).
+pub fn is_valid(svg: &[u8]) -> bool {
+    looks_like_svg(svg) && is_well_formed(svg)
+}
+
+/// Whether a document parses as XML, with a (closed) root element.
+fn is_well_formed(xml: &[u8]) -> bool {
+    let mut reader = Reader::from_reader(xml);
+    let (mut has_root, mut depth) = (false, 0usize);
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Eof) => return has_root && depth == 0,
+            Ok(Event::Start(_)) => {
+                has_root = true;
+                depth += 1;
+            }
+            Ok(Event::Empty(_)) => has_root = true,
+            Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1"/>"#;
+
+    #[test]
+    fn plain_svgs_look_like_svgs() {
+        assert!(looks_like_svg(SVG.as_bytes()));
+        assert!(looks_like_svg(b"<svg>"));
+        assert!(looks_like_svg(b"<svg/>"));
+        assert!(looks_like_svg(b"\n\t <svg\nwidth=\"1\">"));
+    }
+
+    #[test]
+    fn xml_declarations_comments_and_doctypes_are_skipped() {
+        let head = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!-- Generator: Adobe Illustrator -->\n\
+             <!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" \
+             \"http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd\" [\n\
+               <!ENTITY ns \"http://www.w3.org/2000/svg\">\n\
+             ]>\n{SVG}"
+        );
+        assert!(looks_like_svg(head.as_bytes()));
+    }
+
+    #[test]
+    fn byte_order_marks_are_skipped() {
+        assert!(looks_like_svg(format!("\u{feff}{SVG}").as_bytes()));
+        assert!(looks_like_svg(
+            format!("\u{feff}<?xml version=\"1.0\"?>{SVG}").as_bytes()
+        ));
+    }
+
+    #[test]
+    fn html_is_not_an_svg() {
+        assert!(!looks_like_svg(
+            b"<!DOCTYPE html><html><body>Not Found</body></html>"
+        ));
+        assert!(!looks_like_svg(b"<html><body><svg/></body></html>"));
+        assert!(!looks_like_svg(b"<?xml version=\"1.0\"?><html/>"));
+    }
+
+    #[test]
+    fn json_is_not_an_svg() {
+        assert!(!looks_like_svg(br#"{"error":"not found","status":404}"#));
+        assert!(!looks_like_svg(b"[]"));
+    }
+
+    #[test]
+    fn other_content_is_not_an_svg() {
+        assert!(!looks_like_svg(b""));
+        assert!(!looks_like_svg(b"\x89PNG\r\n\x1a\n"));
+        assert!(!looks_like_svg(b"<svgx>"));
+        // a comment cut off before the root element
+        assert!(!looks_like_svg(b"<!-- never ends <svg>"));
+    }
+
+    #[test]
+    fn optimizing_strips_comments_and_metadata() {
+        let svg = br#"<?xml version="1.0"?>
+<!-- Generator: Adobe Illustrator -->
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1">
+  <title>Logo</title>
+  <desc>The <b>company</b> logo</desc>
+  <metadata><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"/></metadata>
+  <desc/>
+  <path d="M0 0h1v1z"/>
+</svg>
+"#;
+
+        assert_eq!(
+            optimize(svg).as_deref(),
+            Some(
+                br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1"><path d="M0 0h1v1z"/></svg>"#
+                    .as_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn optimizing_keeps_whitespace_in_text() {
+        let svg = b"<svg>\n  <text x=\"0\">  ACME <tspan> Corp </tspan>  </text>\n</svg>";
+
+        assert_eq!(
+            optimize(svg).as_deref(),
+            Some(b"<svg><text x=\"0\">  ACME <tspan> Corp </tspan>  </text></svg>".as_slice())
+        );
+    }
+
+    #[test]
+    fn optimizing_malformed_svgs_gives_up() {
+        assert_eq!(optimize(b"<svg><path></svg>"), None);
+        assert_eq!(optimize(b"<svg><title>Logo</svg>"), None);
+        assert_eq!(optimize(b"<svg><path d=\"M0 0\"/>"), None);
+        assert_eq!(optimize(b"<html/>"), None);
+        assert_eq!(optimize(b""), None);
+    }
+
+    #[test]
+    fn optimizing_is_deterministic() {
+        let svg = b"<!-- c --><svg viewBox=\"0 0 1 1\">\n\t<g>\n\t\t<title>t</title><path d=\"M0 0\"/>\n\t</g>\n</svg>\n";
+
+        let once = optimize(svg).unwrap();
+        assert_eq!(optimize(svg).unwrap(), once);
+        assert_eq!(optimize(&once).unwrap(), once);
+    }
+
+    #[test]
+    fn sizing_squares_the_view_box() {
+        let sized = normalize_size(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 200 100"><path d="M0 0"/></svg>"#,
+            64,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sized.as_ref(),
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64" viewBox="0 -50 200 200"><path d="M0 0"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn sizing_without_a_view_box_uses_the_dimensions() {
+        for svg in [
+            r#"<svg width="50" height="100"/>"#,
+            r#"<svg width="50px" height=" 100 px"/>"#,
+        ] {
+            assert_eq!(
+                normalize_size(svg.as_bytes(), 32).unwrap().as_ref(),
+                br#"<svg width="32" height="32" viewBox="-25 0 100 100"/>"#,
+                "{svg}"
+            );
+        }
+    }
+
+    #[test]
+    fn sizing_needs_dimensions() {
+        assert_eq!(normalize_size(b"<svg/>", 64), None);
+        assert_eq!(
+            normalize_size(br#"<svg width="100%" height="100%"/>"#, 64),
+            None
+        );
+        assert_eq!(normalize_size(br#"<svg width="100"/>"#, 64), None);
+        assert_eq!(normalize_size(br#"<svg viewBox="0 0 0 10"/>"#, 64), None);
+        assert_eq!(normalize_size(br#"<svg viewBox="0 0 10 10">"#, 64), None);
+    }
+
+    #[test]
+    fn sizing_is_idempotent() {
+        let once = normalize_size(br#"<svg viewBox="10 20 30 40" width="3in"><g/></svg>"#, 128)
+            .unwrap()
+            .into_owned();
+
+        let twice = normalize_size(&once, 128).unwrap();
+        assert!(matches!(twice, Cow::Borrowed(_)));
+        assert_eq!(twice.as_ref(), once);
+
+        // but not if sized differently
+        assert!(matches!(normalize_size(&once, 64).unwrap(), Cow::Owned(_)));
+    }
+
+    /// Sanitizes `svg`, asserting that it changed.
+    fn sanitized(svg: &str) -> String {
+        match sanitize(svg.as_bytes()).unwrap() {
+            Cow::Owned(sanitized) => String::from_utf8(sanitized).unwrap(),
+            Cow::Borrowed(_) => panic!("{svg} was left as is"),
+        }
+    }
+
+    #[test]
+    fn clean_svgs_are_borrowed() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" style="fill: red">
+  <style>.a { fill: url(#grad); }</style>
+  <linearGradient id="grad"/>
+  <use href="#a" fill="url('#grad')"/>
+  <image href="data:image/png;base64,AAAA"/>
+  <animate attributeName="opacity" from="0" to="1"/>
+</svg>"##;
+
+        assert!(matches!(sanitize(svg), Some(Cow::Borrowed(s)) if s == svg));
+    }
+
+    #[test]
+    fn sanitizing_malformed_svgs_gives_up() {
+        assert_eq!(sanitize(b"<svg><script></svg>"), None);
+        assert_eq!(sanitize(b"<svg><style>"), None);
+    }
+
+    #[test]
+    fn scripts_and_foreign_objects_are_stripped() {
+        assert_eq!(
+            sanitized("<svg><script>alert(1)</script><path/></svg>"),
+            "<svg><path/></svg>"
+        );
+        assert_eq!(
+            sanitized("<svg><SCRIPT href=\"x.js\"/><foreignObject><div/></foreignObject></svg>"),
+            "<svg></svg>"
+        );
+    }
+
+    #[test]
+    fn event_handlers_are_stripped() {
+        assert_eq!(
+            sanitized(r#"<svg onload="alert(1)"><path ONCLICK="alert(1)" d="M0 0"/></svg>"#),
+            r#"<svg><path d="M0 0"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn external_hrefs_are_stripped() {
+        assert_eq!(
+            sanitized(
+                r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><a href="javascript:alert(1)"/><use xlink:href="https://tracker.example/a.svg#a"/></svg>"#
+            ),
+            r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><a/><use/></svg>"#
+        );
+    }
+
+    #[test]
+    fn animations_of_hrefs_are_stripped() {
+        assert_eq!(
+            sanitized(
+                r##"<svg><a href="#a"><set attributeName="href" to="javascript:alert(1)"/><animate attributeName="xlink:href" values="https://tracker.example/"></animate><text>x</text></a></svg>"##
+            ),
+            r##"<svg><a href="#a"><text>x</text></a></svg>"##
+        );
+    }
+
+    #[test]
+    fn javascript_animation_values_are_stripped() {
+        assert_eq!(
+            sanitized(
+                "<svg><animate attributeName=\"x\" from=\"0\" to=\" java\tscript:alert(1)\"/>\
+                 <animate attributeName=\"y\" values=\"0;JavaScript:alert(1)\"/></svg>"
+            ),
+            "<svg><animate attributeName=\"x\" from=\"0\"/><animate attributeName=\"y\"/></svg>"
+        );
+    }
+
+    #[test]
+    fn external_urls_in_attributes_are_stripped() {
+        assert_eq!(
+            sanitized(
+                r#"<svg style="background: URL( 'https://tracker.example/a.png' )"><path fill="url(//tracker.example/p#g)" d="M0 0"/></svg>"#
+            ),
+            r#"<svg><path d="M0 0"/></svg>"#
+        );
+        // escapes could spell out a url()
+        assert_eq!(
+            sanitized(r#"<svg style="background: u\72l(https://tracker.example/)"/>"#),
+            "<svg/>"
+        );
+    }
+
+    #[test]
+    fn style_elements_with_external_references_are_stripped() {
+        for style in [
+            "<style>@import 'https://tracker.example/a.css';</style>",
+            "<style><![CDATA[.a { background: url(https://tracker.example/a.png) }]]></style>",
+            "<style>&#64;import 'https://tracker.example/a.css';</style>",
+        ] {
+            assert_eq!(
+                sanitized(&format!("<svg>{style}<path/></svg>")),
+                "<svg><path/></svg>",
+                "{style}"
+            );
+        }
+    }
+}
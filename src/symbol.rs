@@ -0,0 +1,373 @@
+//! Typed entries for `symbols.toml`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ticker, tsv, tsv::Tsv};
+
+/// Header names (lowercase) that hold the ticker symbol.
+const TICKER_HEADERS: &[&str] = &["symbol", "ticker", "act symbol"];
+/// Header names (lowercase) that hold the company name.
+const NAME_HEADERS: &[&str] = &["company", "company name", "security name", "name"];
+/// Header names (lowercase) that hold the issue type.
+const ISSUE_TYPE_HEADERS: &[&str] = &["issue type", "security type", "type"];
+
+/// The contents of `symbols.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SymbolsFile {
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default)]
+    pub symbol: Vec<Symbol>,
+}
+
+/// The contents of a `symbols.toml` grouped by a column's values
+/// (see [`group_key`]).
+#[derive(Debug, Serialize)]
+pub struct GroupedSymbolsFile<'a> {
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(flatten)]
+    pub groups: BTreeMap<String, Vec<&'a Symbol>>,
+}
+
+/// The `[_meta]` table of `symbols.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Meta {
+    /// The symbol list's header names as published (entries use
+    /// their [normalized](tsv::normalize_header) forms as keys).
+    #[serde(default)]
+    pub original_headers: Vec<String>,
+}
+
+impl SymbolsFile {
+    /// Parses a `symbols.toml`, whether flat or grouped by a column,
+    /// returning every symbol in it.
+    pub fn parse(content: &str) -> Result<Vec<Symbol>, toml::de::Error> {
+        let table = toml::from_str::<toml::Table>(content)?;
+
+        let mut symbols = Vec::new();
+        for (key, value) in table {
+            if key != "_meta" {
+                symbols.extend(value.try_into::<Vec<Symbol>>()?);
+            }
+        }
+
+        Ok(symbols)
+    }
+}
+
+/// The `symbols.toml` table name for a group-by column value:
+/// lowercased, with runs of anything but ASCII letters and digits
+/// replaced by `_`. Empty values group under `unknown`.
+pub fn group_key(value: &str) -> String {
+    let key = tsv::normalize_header(value);
+    if key.is_empty() {
+        "unknown".to_string()
+    } else {
+        key
+    }
+}
+
+/// A single listed security.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Symbol {
+    /// The ticker, as spelled by the exchange.
+    pub ticker: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub company_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_type: Option<String>,
+    /// The exchange(s) the symbol was listed on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<String>,
+    /// The file name (relative to the output directory) that the
+    /// symbol's logo is saved under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_file: Option<String>,
+    /// Every other column from the source list, keyed by its
+    /// [normalized](tsv::normalize_header) header name (sorted, for
+    /// stable output).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Symbol {
+    /// Maps a row of the given symbol list to a `Symbol` (with its
+    /// logo file name filled in), returning `None` if the row has
+    /// no ticker.
+    pub fn from_row(tsv: &Tsv, row: &[String]) -> Option<Self> {
+        let find = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| tsv.find_header_index_case_insensitive(name))
+                .map(|i| tsv.headers()[i].as_str())
+        };
+
+        let ticker_header = find(TICKER_HEADERS)?;
+        let name_header = find(NAME_HEADERS);
+        let issue_type_header = find(ISSUE_TYPE_HEADERS);
+
+        let value = |header: Option<&str>| {
+            header
+                .and_then(|h| tsv.get(row, h))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+
+        let ticker = value(Some(ticker_header))?;
+
+        let extra = tsv
+            .headers()
+            .iter()
+            .zip(row)
+            .filter(|(k, _)| {
+                let k = Some(k.as_str());
+                k != Some(ticker_header)
+                    && k != name_header
+                    && k != issue_type_header
+                    && k != Some("exchange")
+            })
+            .map(|(k, v)| (tsv::normalize_header(k), v.clone()))
+            .collect();
+
+        Some(Self {
+            logo_file: ticker::file_stem(&ticker).map(|stem| format!("{stem}.svg")),
+            ticker,
+            company_name: value(name_header),
+            issue_type: value(issue_type_header),
+            exchange: value(Some("exchange")),
+            extra,
+        })
+    }
+}
+
+/// Serializes symbols as CSV: `ticker` and `company_name` first,
+/// then every other field (including each `extra` column) sorted
+/// by name. Missing values are written as empty fields.
+pub fn to_csv(symbols: &[Symbol]) -> String {
+    let mut columns = BTreeSet::new();
+    for symbol in symbols {
+        columns.extend(symbol.extra.keys().map(String::as_str));
+    }
+    columns.extend(["issue_type", "exchange", "logo_file"]);
+
+    let headers = ["ticker", "company_name"]
+        .into_iter()
+        .chain(columns)
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let rows = symbols.iter().map(|symbol| {
+        headers
+            .iter()
+            .map(|header| {
+                match header.as_str() {
+                    "ticker" => Some(&symbol.ticker),
+                    "company_name" => symbol.company_name.as_ref(),
+                    "issue_type" => symbol.issue_type.as_ref(),
+                    "exchange" => symbol.exchange.as_ref(),
+                    "logo_file" => symbol.logo_file.as_ref(),
+                    extra => symbol.extra.get(extra),
+                }
+                .map_or("", String::as_str)
+            })
+            .collect::<Vec<_>>()
+    });
+
+    tsv::to_delimited(&headers, rows, ',')
+}
+
+/// Renders symbols as a GitHub-flavored Markdown table (Ticker |
+/// Company | Logo), sorted by ticker. `logo_link` gives the
+/// relative link to a symbol's logo, or `None` if it has none (in
+/// which case an em dash is shown).
+pub fn to_markdown(symbols: &[Symbol], logo_link: impl Fn(&Symbol) -> Option<String>) -> String {
+    let mut symbols = symbols.iter().collect::<Vec<_>>();
+    symbols.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+
+    let mut markdown = String::from("| Ticker | Company | Logo |\n| --- | --- | --- |\n");
+
+    for symbol in symbols {
+        let ticker = escape_markdown(&symbol.ticker);
+        let company = escape_markdown(symbol.company_name.as_deref().unwrap_or(""));
+        let logo = logo_link(symbol).map_or_else(
+            || "\u{2014}".to_string(),
+            |link| format!("![{ticker}](<{link}>)"),
+        );
+
+        markdown.push_str(&format!("| {ticker} | {company} | {logo} |\n"));
+    }
+
+    markdown
+}
+
+/// Escapes text for use in a Markdown table cell.
+fn escape_markdown(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#' | '~' | '!' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `tsv` into the `symbols.toml` it's written as.
+    fn symbols_toml(tsv: &str) -> String {
+        let tsv = Tsv::from_delimited(tsv, '\t').unwrap();
+        let mut symbols = tsv
+            .rows()
+            .iter()
+            .map(|row| Symbol::from_row(&tsv, row).unwrap())
+            .collect::<Vec<_>>();
+        symbols.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+
+        toml::to_string_pretty(&SymbolsFile {
+            meta: None,
+            symbol: symbols,
+        })
+        .unwrap()
+    }
+
+    const LIST: &str = "Symbol\tCompany\tUnit Of Trade\tCUSIP\tRound Lot\n\
+                        MSFT\tMicrosoft Corp\t100\t594918104\t100\n\
+                        AAPL\tApple Inc.\t100\t037833100\t100\n\
+                        BRK.A\tBerkshire Hathaway\t1\t084670108\t10\n";
+
+    #[test]
+    fn serialization_is_repeatable() {
+        assert_eq!(symbols_toml(LIST), symbols_toml(LIST));
+    }
+
+    #[test]
+    fn serialization_ignores_row_order() {
+        let mut lines = LIST.lines().collect::<Vec<_>>();
+        lines[1..].reverse();
+        let reversed = lines.join("\n");
+
+        assert_eq!(symbols_toml(LIST), symbols_toml(&reversed));
+    }
+
+    #[test]
+    fn symbols_are_sorted_and_keys_in_a_fixed_order() {
+        let toml = symbols_toml(LIST);
+
+        let tickers = toml
+            .lines()
+            .filter_map(|line| line.strip_prefix("ticker = "))
+            .collect::<Vec<_>>();
+        assert_eq!(tickers, ["\"AAPL\"", "\"BRK.A\"", "\"MSFT\""]);
+
+        let first = toml.split("[[symbol]]").nth(1).unwrap();
+        let keys = first
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [
+                "ticker",
+                "company_name",
+                "logo_file",
+                "cusip",
+                "round_lot",
+                "unit_of_trade"
+            ]
+        );
+    }
+
+    #[test]
+    fn serialization_round_trips() {
+        let toml = symbols_toml(LIST);
+        let parsed = SymbolsFile::parse(&toml).unwrap();
+
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].ticker, "AAPL");
+        assert_eq!(parsed[0].company_name.as_deref(), Some("Apple Inc."));
+        assert_eq!(parsed[0].extra["cusip"], "037833100");
+        assert_eq!(
+            toml::to_string_pretty(&SymbolsFile {
+                meta: None,
+                symbol: parsed,
+            })
+            .unwrap(),
+            toml
+        );
+    }
+
+    fn symbol(ticker: &str, company_name: Option<&str>, extra: &[(&str, &str)]) -> Symbol {
+        Symbol {
+            ticker: ticker.to_string(),
+            company_name: company_name.map(str::to_string),
+            issue_type: None,
+            exchange: None,
+            logo_file: None,
+            extra: extra
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn csv_columns_are_ticker_and_name_then_sorted() {
+        let csv = to_csv(&[
+            symbol("AAPL", Some("Apple Inc."), &[("round_lot", "100")]),
+            symbol("MSFT", None, &[("cusip", "594918104")]),
+        ]);
+
+        assert_eq!(
+            csv.lines().next().unwrap(),
+            "ticker,company_name,cusip,exchange,issue_type,logo_file,round_lot"
+        );
+    }
+
+    #[test]
+    fn csv_missing_values_are_empty_fields() {
+        let csv = to_csv(&[symbol("MSFT", None, &[])]);
+
+        assert_eq!(csv.lines().nth(1).unwrap(), "MSFT,,,,");
+        assert!(!csv.contains("null"));
+    }
+
+    #[test]
+    fn csv_round_trips_through_the_parser() {
+        let symbols = [
+            symbol("AAPL", Some("Apple, Inc."), &[("note", "says \"hi\"")]),
+            symbol(
+                "BRK A",
+                Some("Berkshire\nHathaway"),
+                &[("note", " padded ")],
+            ),
+            symbol("MSFT", None, &[]),
+        ];
+
+        let tsv = Tsv::from_delimited(&to_csv(&symbols), ',').unwrap();
+
+        assert_eq!(tsv.rows().len(), symbols.len());
+        for (symbol, row) in symbols.iter().zip(tsv.rows()) {
+            assert_eq!(tsv.get(row, "ticker"), Some(symbol.ticker.as_str()));
+            assert_eq!(
+                tsv.get(row, "company_name"),
+                Some(symbol.company_name.as_deref().unwrap_or_default())
+            );
+            assert_eq!(
+                tsv.get(row, "note"),
+                Some(symbol.extra.get("note").map_or("", String::as_str))
+            );
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Known exchange test symbols.
+//!
+//! Exchanges publish a handful of dummy tickers in their listings
+//! so that participants can exercise order entry without touching
+//! real securities. None of them have logos, so they're skipped by
+//! default.
+
+/// Test tickers published by NYSE, NASDAQ and CBOE.
+#[rustfmt::skip]
+const TEST_SYMBOLS: &[&str] = &[
+    // NYSE / NYSE American / NYSE Arca
+    "ATEST", "CTEST", "MTEST", "NTEST", "PTEST", "TTEST", "ZTEST",
+    // NASDAQ
+    "ZAZZT", "ZBZZT", "ZCZZT", "ZJZZT", "ZVZZT", "ZWZZT", "ZXZZT", "ZVV", "ZXYZ",
+    // CBOE
+    "ZTST", "ZBZX", "ZEXIT", "ZIEXT", "ZIVZT",
+];
+
+/// Whether the given ticker is a known exchange test symbol.
+///
+/// Class and series suffixes (e.g. `CTEST.A`, `ZXYZ.B`) are ignored
+/// so that every share class of a test ticker is matched.
+pub fn is_test_symbol(symbol: &str) -> bool {
+    let symbol = symbol.trim().to_uppercase();
+    let base = symbol
+        .split(['.', '-', ' ', '/'])
+        .next()
+        .unwrap_or_default();
+
+    TEST_SYMBOLS.contains(&base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::SymbolFilter;
+
+    #[test]
+    fn matches_known_test_symbols() {
+        for symbol in ["ZTST", "ZBZX", "CTEST", "ZVZZT", "ZXYZ"] {
+            assert!(is_test_symbol(symbol), "{symbol}");
+        }
+    }
+
+    #[test]
+    fn matches_regardless_of_case_and_whitespace() {
+        assert!(is_test_symbol(" ctest "));
+        assert!(is_test_symbol("zTsT"));
+    }
+
+    #[test]
+    fn matches_share_classes_of_test_symbols() {
+        for symbol in ["CTEST.A", "ZXYZ.B", "ATEST-W", "ZTST PR", "MTEST/U"] {
+            assert!(is_test_symbol(symbol), "{symbol}");
+        }
+    }
+
+    #[test]
+    fn ignores_real_symbols() {
+        for symbol in ["AAPL", "BRK.A", "TEST", "ZTS", "CTESTX", ""] {
+            assert!(!is_test_symbol(symbol), "{symbol}");
+        }
+    }
+
+    #[test]
+    fn filter_skips_test_symbols_by_default() {
+        let filter = SymbolFilter::default();
+        assert!(!filter.keeps("ZTST"));
+        assert!(filter.keeps("AAPL"));
+
+        let filter = SymbolFilter {
+            include_test_symbols: true,
+            ..SymbolFilter::default()
+        };
+        assert!(filter.keeps("ZTST"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Ticker symbol normalization.
+//!
+//! Exchanges spell class shares and preferreds in a variety of
+//! ways (`BRK.B`, `BRK/B`, `BF B`, `ABC PRA`, ...) whereas the logo
+//! CDN only knows a single spelling per security, so every such
+//! ticker is expanded into a list of candidate CDN names to try.
+
+/// Characters that separate a ticker's root from its class or
+/// series suffix.
+const SEPARATORS: &[char] = &['.', '/', ' ', '-'];
+
+/// Splits a ticker into its root and any suffix parts, returning
+/// `None` if it contains anything other than alphanumerics and
+/// separators (or is empty).
+fn parts(symbol: &str) -> Option<Vec<&str>> {
+    let symbol = symbol.trim();
+
+    if symbol.is_empty()
+        || !symbol
+            .chars()
+            .all(|c| c.is_alphanumeric() || SEPARATORS.contains(&c))
+    {
+        return None;
+    }
+
+    let parts = symbol
+        .split(SEPARATORS)
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>();
+
+    (!parts.is_empty()).then_some(parts)
+}
+
+/// The filesystem-safe file stem to save a ticker's logo under
+/// (e.g. `BRK.B` becomes `BRK-B`), or `None` if the ticker can't
+/// be represented.
+pub fn file_stem(symbol: &str) -> Option<String> {
+    file_stem_cased(symbol, FileCase::Upper)
+}
+
+/// Like [`file_stem`], but cased as given.
+pub fn file_stem_cased(symbol: &str, case: FileCase) -> Option<String> {
+    let stem = parts(symbol)?.join("-");
+    Some(match case {
+        FileCase::Upper => stem.to_uppercase(),
+        FileCase::Lower => stem.to_lowercase(),
+        FileCase::Original => stem,
+    })
+}
+
+/// How the [file stems](file_stem_cased) logos are saved under are
+/// cased.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileCase {
+    /// Uppercased (`BRK-B`).
+    #[default]
+    Upper,
+    /// Lowercased (`brk-b`), as the logo CDN spells them.
+    Lower,
+    /// As the ticker is spelled.
+    Original,
+}
+
+impl std::str::FromStr for FileCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upper" => Ok(Self::Upper),
+            "lower" => Ok(Self::Lower),
+            "original" => Ok(Self::Original),
+            _ => Err(format!(
+                "invalid file name case '{s}' (expected upper, lower or original)"
+            )),
+        }
+    }
+}
+
+/// The names to try on the logo CDN for the given ticker, in order
+/// of preference. Plain tickers yield a single candidate; class
+/// shares and preferreds yield the dotted, dashed and concatenated
+/// spellings (e.g. `brk.b`, `brk-b`, `brkb`).
+pub fn cdn_candidates(symbol: &str) -> Vec<String> {
+    let Some(parts) = parts(symbol) else {
+        return Vec::new();
+    };
+
+    let parts = parts.iter().map(|p| p.to_lowercase()).collect::<Vec<_>>();
+
+    if parts.len() == 1 {
+        return parts;
+    }
+
+    let mut candidates = Vec::new();
+    let mut push = |candidate: String| {
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    };
+
+    push(parts.join("."));
+    push(parts.join("-"));
+
+    // preferreds are usually spelled e.g. `ABC PRA`; the CDN tends
+    // to know them as `abc.pr.a`/`abc-p-a`
+    if let [root, suffix] = parts.as_slice() {
+        if let Some(series) = suffix.strip_prefix("pr").filter(|s| !s.is_empty()) {
+            push(format!("{root}.pr.{series}"));
+            push(format!("{root}-p-{series}"));
+        }
+    }
+
+    push(parts.concat());
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_symbols_have_no_candidates() {
+        for symbol in ["", "   ", "...", " / "] {
+            assert!(cdn_candidates(symbol).is_empty(), "{symbol:?}");
+            assert_eq!(file_stem(symbol), None, "{symbol:?}");
+        }
+    }
+
+    #[test]
+    fn class_shares_try_dotted_then_dashed() {
+        assert_eq!(cdn_candidates("BRK.B"), ["brk.b", "brk-b", "brkb"]);
+        assert_eq!(file_stem("BRK.B").as_deref(), Some("BRK-B"));
+    }
+
+    #[test]
+    fn preferreds_try_the_series_spellings() {
+        assert_eq!(
+            cdn_candidates("ABC PRA"),
+            ["abc.pra", "abc-pra", "abc.pr.a", "abc-p-a", "abcpra"]
+        );
+        assert_eq!(file_stem("ABC PRA").as_deref(), Some("ABC-PRA"));
+    }
+
+    #[test]
+    fn separators_map_to_dashes_in_file_stems() {
+        for symbol in ["BF/B", "BF B", "BF.B", "BF-B", " BF / B "] {
+            assert_eq!(file_stem(symbol).as_deref(), Some("BF-B"), "{symbol:?}");
+        }
+
+        assert_eq!(
+            file_stem_cased("Bf/b", FileCase::Lower).as_deref(),
+            Some("bf-b")
+        );
+        assert_eq!(
+            file_stem_cased("Bf/b", FileCase::Original).as_deref(),
+            Some("Bf-b")
+        );
+        assert_eq!(file_stem("BRK:B"), None);
+    }
+}
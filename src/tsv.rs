@@ -0,0 +1,1179 @@
+//! Parsing of delimited text and spreadsheet symbol lists.
+
+use std::{collections::HashMap, io::Cursor};
+
+use calamine::Reader;
+use encoding_rs::{Encoding, WINDOWS_1252};
+use log::{trace, warn};
+
+use crate::{Error, Result};
+
+/// A parsed symbol list. Headers are stored once; each row holds
+/// its values in header order.
+#[derive(Debug, Default)]
+pub struct Tsv {
+    headers: Vec<String>,
+    /// Maps each header to its column index.
+    header_index: HashMap<String, usize>,
+    /// The (1-based) line or row the headers were on.
+    header_line: usize,
+    rows: Vec<Vec<String>>,
+}
+
+impl Tsv {
+    /// Creates an empty symbol list with the given headers.
+    pub fn new(headers: Vec<String>) -> Self {
+        let mut header_index = HashMap::new();
+        for (i, header) in headers.iter().enumerate() {
+            header_index.entry(header.clone()).or_insert(i);
+        }
+
+        Self {
+            headers,
+            header_index,
+            header_line: 1,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// The rows, each holding its values in header order.
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    pub fn rows_mut(&mut self) -> &mut Vec<Vec<String>> {
+        &mut self.rows
+    }
+
+    /// The values of the column with the given (case-insensitive)
+    /// header, or `None` if there's no such column.
+    pub fn column(&self, name: &str) -> Option<impl Iterator<Item = &str>> {
+        let i = self.find_header_index_case_insensitive(name)?;
+        Some(self.rows.iter().map(move |row| row[i].as_str()))
+    }
+
+    /// The index of the column with exactly the given header.
+    pub fn column_index(&self, header: &str) -> Option<usize> {
+        self.header_index.get(header).copied()
+    }
+
+    /// The value of the given column in the given row.
+    pub fn get<'a>(&self, row: &'a [String], header: &str) -> Option<&'a str> {
+        row.get(self.column_index(header)?).map(String::as_str)
+    }
+
+    /// Adds a column (filled with empty values) if there isn't one
+    /// with the given header already, returning its index.
+    pub fn add_column(&mut self, header: &str) -> usize {
+        if let Some(i) = self.column_index(header) {
+            return i;
+        }
+
+        let i = self.headers.len();
+        self.headers.push(header.to_string());
+        self.header_index.insert(header.to_string(), i);
+        for row in &mut self.rows {
+            row.push(String::new());
+        }
+        i
+    }
+
+    /// Parses raw symbol list content, detecting spreadsheet workbooks
+    /// and falling back to delimited text otherwise. Text is decoded
+    /// with the given encoding, or sniffed if `None` (see
+    /// [`TsvParser::new`]).
+    pub fn parse(
+        content: &[u8],
+        delimiter: char,
+        encoding: Option<&'static Encoding>,
+    ) -> Result<Self> {
+        trace!("symbol list size: {} bytes", content.len());
+
+        if is_spreadsheet(content) {
+            trace!("parsing as XLS/XLSX workbook...");
+            Self::from_spreadsheet(content)
+        } else {
+            trace!("parsing as delimited text ({delimiter:?})...");
+            check_delimited(content, delimiter)?;
+            let mut parser = TsvParser::with_encoding(delimiter, encoding);
+            parser.feed_bytes(content);
+            parser.finish()
+        }
+    }
+
+    /// Parses delimited text, treating the first record as the
+    /// headers. Fields may be quoted RFC 4180-style; a leading
+    /// UTF-8 BOM and CRLF (or CR) line endings are tolerated.
+    pub fn from_delimited(s: &str, delimiter: char) -> Result<Self> {
+        let mut parser = TsvParser::new(delimiter);
+        parser.feed_str(s);
+        parser.finish()
+    }
+
+    /// Converts the first sheet of an XLS/XLSX workbook into
+    /// a `Tsv`, treating the first row as the headers.
+    pub fn from_spreadsheet(data: &[u8]) -> Result<Self> {
+        let mut workbook = calamine::open_workbook_auto_from_rs(Cursor::new(data))?;
+        let range = workbook
+            .worksheet_range_at(0)
+            .ok_or_else(|| Error::symbol_list("workbook has no sheets"))??;
+
+        let mut ignored = 0;
+        let mut sheet_rows = range
+            .rows()
+            .map(|r| {
+                r.iter()
+                    .map(|c| c.to_string().trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .enumerate()
+            .filter(|(_, fields)| {
+                let blank = fields.iter().all(|f| f.is_empty());
+                ignored += usize::from(blank);
+                !blank
+            });
+
+        let (header_row, headers) = sheet_rows
+            .next()
+            .ok_or_else(|| Error::symbol_list("missing headers"))?;
+        let mut tsv = Self::new(headers);
+        tsv.header_line = header_row + 1;
+        for (i, fields) in sheet_rows.by_ref() {
+            let row = Self::make_row(&tsv.headers, fields.into_iter(), i + 1);
+            tsv.rows.push(row);
+        }
+        drop(sheet_rows);
+
+        tsv.drop_junk_rows(ignored);
+        Ok(tsv)
+    }
+
+    /// Checks for columns that share a (case-insensitive) header
+    /// name. Unless `tolerate` is set, any duplicate is an error naming
+    /// the column and its positions; otherwise later duplicates are
+    /// renamed with a numeric suffix (e.g. `Name_2`) with a warning.
+    pub fn resolve_duplicate_headers(&mut self, tolerate: bool) -> Result<()> {
+        let mut positions = HashMap::<String, Vec<usize>>::new();
+        for (i, header) in self.headers.iter().enumerate() {
+            positions
+                .entry(header.trim().to_lowercase())
+                .or_default()
+                .push(i);
+        }
+
+        let mut duplicates = positions
+            .into_values()
+            .filter(|p| p.len() > 1)
+            .collect::<Vec<_>>();
+        duplicates.sort();
+
+        let Some(first) = duplicates.first() else {
+            return Ok(());
+        };
+
+        if !tolerate {
+            return Err(Error::SymbolListParse {
+                line: Some(self.header_line),
+                reason: format!(
+                    "duplicate column '{}' (at columns {})",
+                    self.headers[first[0]],
+                    first
+                        .iter()
+                        .map(|i| (i + 1).to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            });
+        }
+
+        for positions in duplicates {
+            for (n, &i) in positions.iter().enumerate().skip(1) {
+                let mut suffix = n + 1;
+                let renamed = loop {
+                    let candidate = format!("{}_{suffix}", self.headers[i]);
+                    if self
+                        .find_header_index_case_insensitive(&candidate)
+                        .is_none()
+                    {
+                        break candidate;
+                    }
+                    suffix += 1;
+                };
+
+                warn!(
+                    "renaming duplicate column '{}' (column {}) to '{renamed}'",
+                    self.headers[i],
+                    i + 1
+                );
+                self.headers[i] = renamed;
+            }
+        }
+
+        self.header_index.clear();
+        for (i, header) in self.headers.iter().enumerate() {
+            self.header_index.entry(header.clone()).or_insert(i);
+        }
+
+        Ok(())
+    }
+
+    /// Drops rows without a symbol as well as footer rows (e.g. a
+    /// trailing disclaimer paragraph, which shows up as a lone cell
+    /// of prose in a list with other columns), logging how many lines
+    /// were ignored in total.
+    fn drop_junk_rows(&mut self, mut ignored: usize) {
+        if let Ok(symbol) = self.symbol_column() {
+            let before = self.rows.len();
+            let mut rows = std::mem::take(&mut self.rows);
+            rows.retain(|row| !self.is_junk_row(symbol, row));
+            self.rows = rows;
+            ignored += before - self.rows.len();
+        }
+
+        if ignored > 0 {
+            trace!("ignored {ignored} blank, footer or symbol-less lines");
+        }
+    }
+
+    /// Whether a row (of this list, whose symbol column is `symbol`)
+    /// has no symbol, or is a footer row (see
+    /// [`drop_junk_rows`](Self::drop_junk_rows)).
+    fn is_junk_row(&self, symbol: usize, row: &[String]) -> bool {
+        // in a single-column list every row is a lone cell, and
+        // tickers like `BRK A WI` aren't to be mistaken for prose
+        let has_other_columns = self.headers.len() > 1;
+
+        let value = row[symbol].trim();
+        let lone = row
+            .iter()
+            .enumerate()
+            .all(|(i, v)| i == symbol || v.is_empty());
+
+        value.is_empty() || (has_other_columns && lone && is_prose(value))
+    }
+
+    /// Merges several symbol lists into one, keeping only the first
+    /// row seen for each (uppercase) ticker. Each list's symbol column
+    /// is renamed to the first list's spelling so that lookups work
+    /// uniformly on the merged result.
+    ///
+    /// When a duplicate ticker carries an `exchange` tag, it is appended
+    /// to the kept row's tag so the row records every list it was on.
+    pub fn merge(lists: Vec<Tsv>) -> Result<Self> {
+        let Some(first) = lists.first() else {
+            return Err(Error::Format("no symbol lists to merge".to_string()));
+        };
+
+        let mut merged = Self::new(Vec::new());
+        let merged_symbol = merged.add_column(&first.headers()[first.symbol_column()?]);
+        let mut seen = HashMap::<String, usize>::new();
+
+        for list in lists {
+            let symbol = list.symbol_column()?;
+            let exchange = list.column_index("exchange");
+
+            // where each of this list's columns ends up in the merged list
+            let columns = list
+                .headers()
+                .iter()
+                .enumerate()
+                .map(|(i, header)| {
+                    if i == symbol {
+                        merged_symbol
+                    } else {
+                        merged.add_column(header)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let merged_exchange = merged.column_index("exchange");
+
+            for row in list.rows {
+                let value = &row[symbol];
+
+                if let Some(&existing) = seen.get(&value.trim().to_uppercase()) {
+                    trace!("ignoring duplicate symbol '{}'", value.trim());
+
+                    if let (Some(exchange), Some(merged_exchange)) = (exchange, merged_exchange) {
+                        let exchange = &row[exchange];
+                        let tag = &mut merged.rows[existing][merged_exchange];
+                        if tag.is_empty() {
+                            tag.clone_from(exchange);
+                        } else if !tag.split(", ").any(|t| t == exchange) {
+                            tag.push_str(", ");
+                            tag.push_str(exchange);
+                        }
+                    }
+
+                    continue;
+                }
+
+                seen.insert(value.trim().to_uppercase(), merged.rows.len());
+
+                let mut merged_row = vec![String::new(); merged.headers().len()];
+                for (i, value) in row.into_iter().enumerate() {
+                    merged_row[columns[i]] = value;
+                }
+                merged.rows.push(merged_row);
+            }
+        }
+
+        trace!(
+            "merged symbol lists into {} unique symbols",
+            merged.rows.len()
+        );
+
+        Ok(merged)
+    }
+
+    /// Zips a line's fields up with the headers. Missing trailing
+    /// fields become empty strings; surplus fields are dropped (with
+    /// a warning, unless they're empty, e.g. from trailing tabs).
+    fn make_row(
+        headers: &[String],
+        fields: impl Iterator<Item = String>,
+        line: usize,
+    ) -> Vec<String> {
+        let mut fields = fields.collect::<Vec<_>>();
+
+        if fields.len() > headers.len() {
+            if fields[headers.len()..].iter().any(|f| !f.is_empty()) {
+                warn!(
+                    "line {line}: expected {} fields but found {}; ignoring the surplus",
+                    headers.len(),
+                    fields.len()
+                );
+            } else {
+                trace!("line {line}: ignoring trailing empty fields");
+            }
+            fields.truncate(headers.len());
+        }
+
+        fields.resize(headers.len(), String::new());
+        fields
+    }
+
+    /// The index of the `symbol` column, with an error listing the
+    /// headers that were found if there isn't one.
+    pub fn symbol_column(&self) -> Result<usize> {
+        self.require_column("symbol")
+    }
+
+    /// The index of the named column (matched case-insensitively),
+    /// with an error listing the headers that were found if there
+    /// isn't one.
+    pub fn require_column(&self, name: &str) -> Result<usize> {
+        self.find_header_index_case_insensitive(name)
+            .ok_or_else(|| Error::MissingColumn {
+                column: name.to_string(),
+                available: self.headers.iter().map(|h| format!("{h:?}")).collect(),
+            })
+    }
+
+    pub fn find_header_index_case_insensitive(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.headers.iter().position(|h| h.to_lowercase() == name)
+    }
+
+    /// Checks that no two headers have the same
+    /// [normalized](normalize_header) name, since they would clash
+    /// as keys in the serialized metadata.
+    pub fn check_normalized_headers(&self) -> Result<()> {
+        let mut seen = HashMap::<String, &str>::new();
+        for header in &self.headers {
+            let key = normalize_header(header);
+            if let Some(other) = seen.insert(key.clone(), header) {
+                return Err(Error::SymbolListParse {
+                    line: Some(self.header_line),
+                    reason: format!("columns '{other}' and '{header}' both normalize to '{key}'"),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops every column but the named ones (matched by their
+    /// [normalized](normalize_header) names) and the symbol column,
+    /// keeping their original order. Unknown names are an error.
+    pub fn select_columns(&mut self, names: &[String]) -> Result<()> {
+        let normalized = self
+            .headers
+            .iter()
+            .map(|h| normalize_header(h))
+            .collect::<Vec<_>>();
+
+        let mut keep = vec![false; self.headers.len()];
+        keep[self.symbol_column()?] = true;
+
+        for name in names {
+            let name = normalize_header(name);
+            let i =
+                normalized
+                    .iter()
+                    .position(|h| *h == name)
+                    .ok_or_else(|| Error::MissingColumn {
+                        column: name.clone(),
+                        available: normalized.clone(),
+                    })?;
+            keep[i] = true;
+        }
+
+        let retain = |values: &mut Vec<String>| {
+            let mut keep = keep.iter();
+            values.retain(|_| keep.next().copied().unwrap_or(false));
+        };
+
+        retain(&mut self.headers);
+        for row in &mut self.rows {
+            retain(row);
+        }
+
+        self.header_index.clear();
+        for (i, header) in self.headers.iter().enumerate() {
+            self.header_index.entry(header.clone()).or_insert(i);
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a lone cell reads like a sentence of footer text rather
+/// than a ticker: four or more words, or a few ending in sentence
+/// punctuation (e.g. `See disclaimer below.`).
+fn is_prose(value: &str) -> bool {
+    let words = value.split_whitespace().count();
+    words >= 4 || (words >= 2 && value.ends_with(['.', ':', '!', '?']))
+}
+
+/// Normalizes a header name to a `snake_case` key: trimmed and
+/// lowercased, with runs of anything but ASCII letters and digits
+/// replaced by `_` (e.g. `Unit Of Trade` becomes `unit_of_trade`).
+pub fn normalize_header(header: &str) -> String {
+    let mut key = String::with_capacity(header.len());
+    for c in header.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            key.push(c.to_ascii_lowercase());
+        } else if !key.is_empty() && !key.ends_with('_') {
+            key.push('_');
+        }
+    }
+
+    key.trim_end_matches('_').to_string()
+}
+
+/// Guesses the delimiter of a local symbol file from its header
+/// line, picking whichever of tab, comma or pipe occurs most often
+/// (tab wins ties, matching the NYSE format).
+pub fn sniff_delimiter(content: &[u8]) -> char {
+    let header = content.split(|&b| b == b'\n').next().unwrap_or_default();
+    ['\t', ',', '|']
+        .into_iter()
+        .rev()
+        .max_by_key(|&d| header.iter().filter(|&&b| b == d as u8).count())
+        .unwrap_or('\t')
+}
+
+/// OLE2 compound document magic (legacy BIFF `.xls` workbooks).
+const OLE2_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+/// ZIP local file header magic (OOXML `.xlsx` workbooks).
+const ZIP_MAGIC: &[u8] = &[0x50, 0x4B, 0x03, 0x04];
+
+/// Whether the given content looks like an actual spreadsheet
+/// workbook rather than tab-separated text.
+pub fn is_spreadsheet(data: &[u8]) -> bool {
+    data.starts_with(OLE2_MAGIC) || data.starts_with(ZIP_MAGIC)
+}
+
+/// Serializes a table as delimited text, quoting fields RFC
+/// 4180-style where needed (i.e. when they contain the delimiter,
+/// a quote, a line break or surrounding whitespace), so that it
+/// round-trips through [`Tsv::from_delimited`].
+pub fn to_delimited<R, F>(headers: &[String], rows: R, delimiter: char) -> String
+where
+    R: IntoIterator<Item = F>,
+    F: IntoIterator,
+    F::Item: AsRef<str>,
+{
+    let mut out = String::new();
+
+    let mut push_record = |fields: &mut dyn Iterator<Item = &str>| {
+        for (i, field) in fields.enumerate() {
+            if i > 0 {
+                out.push(delimiter);
+            }
+
+            let needs_quotes =
+                field.contains([delimiter, '"', '\n', '\r']) || field.trim() != field;
+            if needs_quotes {
+                out.push('"');
+                out.push_str(&field.replace('"', "\"\""));
+                out.push('"');
+            } else {
+                out.push_str(field);
+            }
+        }
+        out.push('\n');
+    };
+
+    push_record(&mut headers.iter().map(String::as_str));
+    for row in rows {
+        let row = row.into_iter().collect::<Vec<_>>();
+        push_record(&mut row.iter().map(AsRef::as_ref));
+    }
+
+    out
+}
+
+/// Checks that the start of a symbol list looks like delimited
+/// text, rejecting HTML (e.g. an error page served with a 200) and
+/// content whose header line doesn't contain the delimiter (unless
+/// it's a lone `symbol` column).
+pub fn check_delimited(head: &[u8], delimiter: char) -> Result<()> {
+    trace!(
+        "symbol list starts with: {:?}",
+        String::from_utf8_lossy(&head[..head.len().min(200)])
+    );
+
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let lower = text.get(..16).unwrap_or(text).to_lowercase();
+
+    if lower.starts_with("<!doctype") || lower.starts_with("<html") {
+        return Err(Error::UnexpectedContent(
+            "symbol list does not look like delimited text (got HTML)".to_string(),
+        ));
+    }
+
+    let header = text.lines().next().unwrap_or_default().trim();
+    if !header.contains(delimiter) && !header.trim_matches('"').eq_ignore_ascii_case("symbol") {
+        return Err(Error::UnexpectedContent(format!(
+            "symbol list does not look like delimited text (no {delimiter:?} in the header line)"
+        )));
+    }
+
+    Ok(())
+}
+
+/// A streaming parser of delimited text into a `Tsv`, for
+/// parsing a symbol list as it's downloaded rather than buffering
+/// the raw body first. See [`Tsv::from_delimited`] for the format.
+///
+/// Rows are collected into the `Tsv` that [`finish`](Self::finish)
+/// returns, unless they're [taken](Self::take_rows) as they're
+/// parsed, so that a list can be handled row by row without ever
+/// holding all of it.
+pub struct TsvParser {
+    records: RecordParser,
+    tsv: Option<Tsv>,
+    /// The symbol column of `tsv`, if it has one.
+    symbol: Option<usize>,
+    /// Number of blank, footer and symbol-less lines skipped so far.
+    ignored: usize,
+    /// The decoder for a known (or detected) non-UTF-8 encoding;
+    /// while `None`, input is assumed to be UTF-8.
+    decoder: Option<encoding_rs::Decoder>,
+    /// Trailing bytes of an incomplete UTF-8 sequence, carried
+    /// over to the next chunk.
+    leftover: Vec<u8>,
+    started: bool,
+}
+
+impl TsvParser {
+    /// Creates a parser that sniffs the text encoding: input is
+    /// decoded as UTF-8 until an invalid sequence is seen, from
+    /// which point on it's decoded as Windows-1252.
+    pub fn new(delimiter: char) -> Self {
+        Self::with_encoding(delimiter, None)
+    }
+
+    /// Creates a parser that decodes input with the given encoding,
+    /// or sniffs it (see [`TsvParser::new`]) if `None`.
+    pub fn with_encoding(delimiter: char, encoding: Option<&'static Encoding>) -> Self {
+        Self {
+            records: RecordParser::new(delimiter),
+            tsv: None,
+            symbol: None,
+            ignored: 0,
+            decoder: encoding.map(Encoding::new_decoder_without_bom_handling),
+            leftover: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Feeds a chunk of encoded bytes; sequences split across
+    /// chunks are reassembled.
+    pub fn feed_bytes(&mut self, chunk: &[u8]) {
+        if self.decoder.is_some() {
+            self.decode(chunk, false);
+            return;
+        }
+
+        let mut buf = std::mem::take(&mut self.leftover);
+        buf.extend_from_slice(chunk);
+
+        match std::str::from_utf8(&buf) {
+            Ok(s) => self.feed_str(s),
+            Err(e) => {
+                let (valid, rest) = buf.split_at(e.valid_up_to());
+                self.feed_str(&String::from_utf8_lossy(valid));
+
+                if e.error_len().is_some() {
+                    self.fall_back_to_windows_1252();
+                    self.decode(rest, false);
+                } else {
+                    self.leftover = rest.to_vec();
+                }
+            }
+        }
+    }
+
+    fn fall_back_to_windows_1252(&mut self) {
+        trace!("symbol list is not valid UTF-8; decoding the rest as Windows-1252");
+        self.decoder = Some(WINDOWS_1252.new_decoder_without_bom_handling());
+    }
+
+    fn decode(&mut self, bytes: &[u8], last: bool) {
+        let Some(mut decoder) = self.decoder.take() else {
+            return;
+        };
+
+        let mut out = String::with_capacity(
+            decoder
+                .max_utf8_buffer_length(bytes.len())
+                .unwrap_or(bytes.len() * 3),
+        );
+        let _ = decoder.decode_to_string(bytes, &mut out, last);
+        self.decoder = Some(decoder);
+
+        self.feed_str(&out);
+    }
+
+    /// Feeds a chunk of text.
+    pub fn feed_str(&mut self, s: &str) {
+        let mut s = s;
+        if !self.started && !s.is_empty() {
+            self.started = true;
+            s = s.strip_prefix('\u{feff}').unwrap_or(s);
+        }
+
+        for c in s.chars() {
+            if let Some(record) = self.records.push(c) {
+                self.push_record(record);
+            }
+        }
+    }
+
+    fn push_record(&mut self, (line, fields): (usize, Vec<String>)) {
+        if fields.iter().all(|f| f.is_empty()) {
+            self.ignored += 1;
+            return;
+        }
+
+        match &mut self.tsv {
+            None => {
+                let mut tsv = Tsv::new(fields);
+                tsv.header_line = line;
+                self.symbol = tsv.symbol_column().ok();
+                self.tsv = Some(tsv);
+            }
+            Some(tsv) => {
+                let row = Tsv::make_row(&tsv.headers, fields.into_iter(), line);
+                if self
+                    .symbol
+                    .is_some_and(|symbol| tsv.is_junk_row(symbol, &row))
+                {
+                    self.ignored += 1;
+                } else {
+                    tsv.rows.push(row);
+                }
+            }
+        }
+    }
+
+    /// Takes the rows parsed so far (without blank, footer and
+    /// symbol-less ones), along with the list they belong to (with
+    /// its headers, but none of its rows), or returns `None` if not
+    /// even the headers have been parsed yet. Taken rows are left out
+    /// of the list [`finish`](Self::finish) returns.
+    pub fn take_rows(&mut self) -> Option<(&Tsv, Vec<Vec<String>>)> {
+        let tsv = self.tsv.as_mut()?;
+        let rows = std::mem::take(&mut tsv.rows);
+        Some((tsv, rows))
+    }
+
+    /// Finishes parsing, returning the parsed symbol list.
+    pub fn finish(mut self) -> Result<Tsv> {
+        if !self.leftover.is_empty() {
+            // an incomplete UTF-8 sequence at the very end
+            let leftover = std::mem::take(&mut self.leftover);
+            self.fall_back_to_windows_1252();
+            self.decode(&leftover, false);
+        }
+
+        if self.decoder.is_some() {
+            self.decode(&[], true);
+        }
+
+        let records = std::mem::replace(&mut self.records, RecordParser::new(','));
+        if let Some(record) = records.finish() {
+            self.push_record(record);
+        }
+
+        let mut tsv = self
+            .tsv
+            .ok_or_else(|| Error::symbol_list("missing headers"))?;
+        tsv.drop_junk_rows(self.ignored);
+        Ok(tsv)
+    }
+}
+
+/// An incremental splitter of delimited text into records of
+/// fields, each tagged with the (1-based) line it starts on. Text
+/// is fed in one `char` at a time so that it can be parsed as it
+/// arrives.
+///
+/// Fields may be wrapped in double quotes, in which case delimiters
+/// and newlines inside them are taken literally and doubled quotes
+/// (`""`) are unescaped. Unquoted fields are trimmed. CRLF and lone
+/// CR line endings are treated as plain newlines (including within
+/// quoted fields).
+struct RecordParser {
+    delimiter: char,
+    fields: Vec<String>,
+    field: String,
+    /// Whether the current field started with a quote.
+    quoted: bool,
+    /// Whether we're between a field's opening and closing quotes.
+    in_quotes: bool,
+    /// Whether the last character was a quote inside a quoted
+    /// field, which is either the closing quote or the first half
+    /// of an escaped (doubled) quote.
+    pending_quote: bool,
+    /// Whether the last character was a `\r`, which ended the line
+    /// itself, so that a `\n` following it is dropped.
+    pending_cr: bool,
+    line: usize,
+    record_line: usize,
+}
+
+impl RecordParser {
+    fn new(delimiter: char) -> Self {
+        Self {
+            delimiter,
+            fields: Vec::new(),
+            field: String::new(),
+            quoted: false,
+            in_quotes: false,
+            pending_quote: false,
+            pending_cr: false,
+            line: 1,
+            record_line: 1,
+        }
+    }
+
+    /// Feeds a single character, returning a record if it completed one.
+    fn push(&mut self, c: char) -> Option<(usize, Vec<String>)> {
+        if std::mem::take(&mut self.pending_cr) && c == '\n' {
+            return None;
+        }
+
+        if c == '\r' {
+            self.pending_cr = true;
+            return self.push_char('\n');
+        }
+
+        self.push_char(c)
+    }
+
+    fn push_char(&mut self, c: char) -> Option<(usize, Vec<String>)> {
+        if std::mem::take(&mut self.pending_quote) {
+            if c == '"' {
+                self.field.push('"');
+                return None;
+            }
+
+            self.in_quotes = false;
+        }
+
+        if self.in_quotes {
+            match c {
+                '"' => self.pending_quote = true,
+                '\n' => {
+                    self.line += 1;
+                    self.field.push(c);
+                }
+                _ => self.field.push(c),
+            }
+            return None;
+        }
+
+        match c {
+            '"' if !self.quoted && self.field.trim().is_empty() => {
+                self.field.clear();
+                self.quoted = true;
+                self.in_quotes = true;
+            }
+            '\n' => {
+                self.finish_field();
+                let fields = Vec::with_capacity(self.fields.len());
+                let record = (
+                    self.record_line,
+                    std::mem::replace(&mut self.fields, fields),
+                );
+                self.line += 1;
+                self.record_line = self.line;
+                return Some(record);
+            }
+            c if c == self.delimiter => self.finish_field(),
+            // whitespace after a closing quote is dropped, any other
+            // text is kept verbatim (lenient, rather than rejecting
+            // the row outright)
+            c if self.quoted && c.is_whitespace() => {}
+            _ => self.field.push(c),
+        }
+
+        None
+    }
+
+    /// Copies the field out of the buffer (which is kept, so that
+    /// each field costs a single allocation of just its size).
+    fn finish_field(&mut self) {
+        let value = if self.quoted {
+            self.field.as_str()
+        } else {
+            self.field.trim()
+        };
+        self.fields.push(value.to_string());
+        self.field.clear();
+        self.quoted = false;
+    }
+
+    /// Flushes the final record, if the text didn't end in a newline.
+    fn finish(mut self) -> Option<(usize, Vec<String>)> {
+        if self.in_quotes && !self.pending_quote {
+            warn!("line {}: unterminated quoted field", self.record_line);
+        }
+
+        if self.quoted || !self.field.is_empty() || !self.fields.is_empty() {
+            self.finish_field();
+            Some((self.record_line, self.fields))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Tsv {
+        Tsv::from_delimited(s, '\t').unwrap()
+    }
+
+    #[test]
+    fn short_rows_are_padded_with_empty_values() {
+        let tsv = parse("Symbol\tCompany\tExchange\nAAPL\tApple\nMSFT\n");
+
+        assert_eq!(tsv.rows()[0], ["AAPL", "Apple", ""]);
+        assert_eq!(tsv.rows()[1], ["MSFT", "", ""]);
+    }
+
+    #[test]
+    fn surplus_fields_are_dropped() {
+        let tsv = parse("Symbol\tCompany\nAAPL\tApple\tsurplus\textra\n");
+
+        assert_eq!(tsv.rows(), [["AAPL", "Apple"]]);
+    }
+
+    #[test]
+    fn trailing_tabs_are_ignored() {
+        let tsv = parse("Symbol\tCompany\t\nAAPL\tApple\t\t\nMSFT\tMicrosoft\n");
+
+        assert_eq!(tsv.headers(), ["Symbol", "Company", ""]);
+        assert_eq!(tsv.rows()[0], ["AAPL", "Apple", ""]);
+        assert_eq!(tsv.rows()[1], ["MSFT", "Microsoft", ""]);
+    }
+
+    #[test]
+    fn quoted_fields_keep_delimiters() {
+        let tsv = parse("Symbol\tCompany\nAAPL\t\"Apple\tInc.\"\n");
+        assert_eq!(tsv.rows(), [["AAPL", "Apple\tInc."]]);
+
+        let csv = Tsv::from_delimited("Symbol,Company\nSJ,\"Smith, Jones & Co.\"\n", ',').unwrap();
+        assert_eq!(csv.rows(), [["SJ", "Smith, Jones & Co."]]);
+    }
+
+    #[test]
+    fn doubled_quotes_are_unescaped() {
+        let tsv = parse("Symbol\tCompany\nQ\t\"The \"\"Quoted\"\" Co.\"\n");
+
+        assert_eq!(tsv.rows(), [["Q", "The \"Quoted\" Co."]]);
+    }
+
+    #[test]
+    fn quoted_fields_span_lines() {
+        let tsv =
+            parse("Symbol\tCompany\tExchange\nML\t\"Multi\nLine\"\tNYSE\nAAPL\tApple\tNYSE\n");
+
+        assert_eq!(tsv.rows()[0], ["ML", "Multi\nLine", "NYSE"]);
+        assert_eq!(tsv.rows()[1], ["AAPL", "Apple", "NYSE"]);
+    }
+
+    #[test]
+    fn quotes_inside_unquoted_fields_are_literal() {
+        let tsv = parse("Symbol\tCompany\nX\tSay \"hi\"\n");
+
+        assert_eq!(tsv.rows(), [["X", "Say \"hi\""]]);
+    }
+
+    #[test]
+    fn quoted_headers_are_unquoted() {
+        let csv = Tsv::from_delimited("\"Symbol\",\"Company Name\"\nAAPL,Apple\n", ',').unwrap();
+
+        assert_eq!(csv.headers(), ["Symbol", "Company Name"]);
+    }
+
+    #[test]
+    fn streamed_chunks_parse_like_a_whole() {
+        let text = "Symbol\tCompany\r\nAAPL\t\"Apple\r\nInc.\"\r\nMSFT\tMicrosoft\rIBM\tIBM\n";
+        let whole = parse(text);
+
+        for size in 1..text.len() {
+            let mut parser = TsvParser::new('\t');
+            for chunk in text.as_bytes().chunks(size) {
+                parser.feed_bytes(chunk);
+            }
+            let tsv = parser.finish().unwrap();
+
+            assert_eq!(tsv.headers(), whole.headers(), "chunks of {size}");
+            assert_eq!(tsv.rows(), whole.rows(), "chunks of {size}");
+        }
+        assert_eq!(whole.rows().len(), 3);
+    }
+
+    #[test]
+    fn crlf_line_endings_leave_no_stray_cr() {
+        let tsv = parse("Symbol\tCompany\r\nAAPL\tApple\r\nMSFT\tMicrosoft\r\n");
+
+        assert_eq!(tsv.headers(), ["Symbol", "Company"]);
+        assert_eq!(tsv.rows(), [["AAPL", "Apple"], ["MSFT", "Microsoft"]]);
+    }
+
+    #[test]
+    fn lone_cr_line_endings_end_rows() {
+        let tsv = parse("Symbol\tCompany\rAAPL\tApple\rMSFT\tMicrosoft\r");
+
+        assert_eq!(tsv.headers(), ["Symbol", "Company"]);
+        assert_eq!(tsv.rows(), [["AAPL", "Apple"], ["MSFT", "Microsoft"]]);
+    }
+
+    #[test]
+    fn crlf_inside_quoted_fields_is_a_newline() {
+        let tsv = parse("Symbol\tCompany\r\nML\t\"Multi\r\nLine\"\r\n");
+
+        assert_eq!(tsv.rows(), [["ML", "Multi\nLine"]]);
+    }
+
+    #[test]
+    fn leading_bom_is_stripped() {
+        let tsv = parse("\u{feff}Symbol\tCompany\nAAPL\tApple\n");
+
+        assert_eq!(tsv.headers(), ["Symbol", "Company"]);
+        assert_eq!(tsv.symbol_column().unwrap(), 0);
+    }
+
+    #[test]
+    fn bom_and_crlf_together() {
+        let tsv = Tsv::parse(
+            b"\xef\xbb\xbfSymbol\tCompany\r\nAAPL\tApple\r\n",
+            '\t',
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(tsv.headers(), ["Symbol", "Company"]);
+        assert_eq!(tsv.rows(), [["AAPL", "Apple"]]);
+    }
+
+    #[test]
+    fn trailing_junk_is_dropped() {
+        let tsv = Tsv::parse(
+            include_bytes!("../tests/fixtures/trailing_junk.tsv"),
+            '\t',
+            None,
+        )
+        .unwrap();
+
+        let symbols = tsv.column("symbol").unwrap().collect::<Vec<_>>();
+        assert_eq!(symbols, ["AAPL", "BRK A", "MSFT"]);
+    }
+
+    #[test]
+    fn lone_tickers_are_kept_in_single_column_lists() {
+        let tsv = parse("Symbol\nAAPL\nBRK A WI\nABCDEFGHIJKLMNOP\nXYZ PR A WD\n");
+
+        let symbols = tsv.column("symbol").unwrap().collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            ["AAPL", "BRK A WI", "ABCDEFGHIJKLMNOP", "XYZ PR A WD"]
+        );
+    }
+
+    #[test]
+    fn lone_tickers_are_kept_alongside_other_columns() {
+        let tsv = parse(
+            "Symbol\tCompany\nBRK A WI\t\nABCDEFGHIJKLMNOP\t\nNot a ticker, but a sentence.\t\n",
+        );
+
+        let symbols = tsv.column("symbol").unwrap().collect::<Vec<_>>();
+        assert_eq!(symbols, ["BRK A WI", "ABCDEFGHIJKLMNOP"]);
+    }
+
+    #[test]
+    fn duplicate_headers_are_an_error_by_default() {
+        let mut tsv = parse("Symbol\tName\tExchange\t name \nAAPL\tApple\tNYSE\tApple Inc.\n");
+
+        let e = tsv.resolve_duplicate_headers(false).unwrap_err();
+        assert!(
+            matches!(&e, Error::SymbolListParse { line: Some(1), reason } if reason.contains("'Name'") && reason.contains("columns 2, 4")),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn duplicate_headers_are_renamed_when_tolerated() {
+        let mut tsv = parse("Symbol\tName\tNAME\tName_2\nAAPL\tApple\tApple Inc.\tx\n");
+
+        tsv.resolve_duplicate_headers(true).unwrap();
+        assert_eq!(tsv.headers(), ["Symbol", "Name", "NAME_3", "Name_2"]);
+        assert_eq!(tsv.get(&tsv.rows()[0], "NAME_3"), Some("Apple Inc."));
+        assert_eq!(tsv.get(&tsv.rows()[0], "Name"), Some("Apple"));
+    }
+
+    #[test]
+    fn distinct_headers_are_left_alone() {
+        let mut tsv = parse("Symbol\tName\nAAPL\tApple\n");
+
+        tsv.resolve_duplicate_headers(false).unwrap();
+        assert_eq!(tsv.headers(), ["Symbol", "Name"]);
+    }
+
+    const WINDOWS_1252_NAMES: [&str; 3] = ["Apple Inc.", "Nestlé S.A.", "Section § Holdings"];
+
+    #[test]
+    fn windows_1252_is_sniffed() {
+        let tsv = Tsv::parse(
+            include_bytes!("../tests/fixtures/windows_1252.tsv"),
+            '\t',
+            None,
+        )
+        .unwrap();
+
+        let names = tsv.column("Company").unwrap().collect::<Vec<_>>();
+        assert_eq!(names, WINDOWS_1252_NAMES);
+        assert_eq!(tsv.get(&tsv.rows()[2], "Note"), Some("Café — Bistro"));
+    }
+
+    #[test]
+    fn windows_1252_is_decoded_when_given() {
+        let tsv = Tsv::parse(
+            include_bytes!("../tests/fixtures/windows_1252.tsv"),
+            '\t',
+            Some(WINDOWS_1252),
+        )
+        .unwrap();
+
+        let names = tsv.column("Company").unwrap().collect::<Vec<_>>();
+        assert_eq!(names, WINDOWS_1252_NAMES);
+    }
+
+    #[test]
+    fn windows_1252_is_sniffed_across_chunks() {
+        let mut parser = TsvParser::new('\t');
+        for byte in include_bytes!("../tests/fixtures/windows_1252.tsv") {
+            parser.feed_bytes(std::slice::from_ref(byte));
+        }
+        let tsv = parser.finish().unwrap();
+
+        let names = tsv.column("Company").unwrap().collect::<Vec<_>>();
+        assert_eq!(names, WINDOWS_1252_NAMES);
+    }
+
+    #[test]
+    fn rows_can_be_taken_as_they_are_parsed() {
+        let mut parser = TsvParser::new('\t');
+        parser.feed_str("Symbol\tCompany\n");
+        let (tsv, rows) = parser.take_rows().unwrap();
+        assert_eq!(tsv.headers(), ["Symbol", "Company"]);
+        assert!(rows.is_empty());
+
+        parser.feed_str("AAPL\tApple Inc.\n\tNo ticker\nMSFT\tMicro");
+        let (_, rows) = parser.take_rows().unwrap();
+        assert_eq!(rows, [["AAPL", "Apple Inc."]]);
+
+        parser.feed_str("soft\nThis list is provided for information only.\nXOM\tExxon\n");
+        let (_, rows) = parser.take_rows().unwrap();
+        assert_eq!(rows, [["MSFT", "Microsoft"], ["XOM", "Exxon"]]);
+
+        // only the rows that weren't taken are left
+        parser.feed_str("IBM\tIBM");
+        let tsv = parser.finish().unwrap();
+        assert_eq!(tsv.rows(), [["IBM", "IBM"]]);
+    }
+
+    #[test]
+    fn rows_cant_be_taken_before_the_headers() {
+        let mut parser = TsvParser::new('\t');
+        assert!(parser.take_rows().is_none());
+        parser.feed_str("Symbol\tComp");
+        assert!(parser.take_rows().is_none());
+    }
+
+    #[test]
+    fn utf8_split_across_chunks_stays_utf8() {
+        let content = "Symbol\tCompany\nNSRGY\tNestlé S.A.\nSECT\tSection § Holdings\n";
+        let mut parser = TsvParser::new('\t');
+        for byte in content.as_bytes() {
+            parser.feed_bytes(std::slice::from_ref(byte));
+        }
+        let tsv = parser.finish().unwrap();
+
+        let names = tsv.column("Company").unwrap().collect::<Vec<_>>();
+        assert_eq!(names, ["Nestlé S.A.", "Section § Holdings"]);
+    }
+
+    #[test]
+    fn delimited_output_round_trips() {
+        let headers = ["Symbol", "Name, Inc.", "Note"].map(str::to_string);
+        let rows = [
+            ["AAPL", "Apple, Inc.", "plain"],
+            ["BRK A", "say \"cheese\"", "two\nlines"],
+            ["MSFT", "", " padded "],
+            ["XOM", "tab\there", "crlf\r\nline"],
+        ];
+
+        for delimiter in ['\t', ','] {
+            let text = to_delimited(&headers, rows, delimiter);
+            let tsv = Tsv::from_delimited(&text, delimiter).unwrap();
+
+            assert_eq!(tsv.headers(), headers);
+            let mut expected = rows.map(|row| row.map(str::to_string));
+            // line breaks in quoted fields come back as `\n`
+            expected[3][2] = "crlf\nline".to_string();
+            assert_eq!(tsv.rows(), expected);
+        }
+    }
+
+    #[test]
+    fn delimited_output_only_quotes_when_needed() {
+        let headers = ["a", "b", "c"].map(str::to_string);
+        let text = to_delimited(&headers, [["x", "y,z", "q\"r"]], ',');
+
+        assert_eq!(text, "a,b,c\nx,\"y,z\",\"q\"\"r\"\n");
+    }
+}
@@ -0,0 +1,184 @@
+//! Auditing the logos in an output directory.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::Read,
+    path::Path,
+};
+
+use flate2::read::GzDecoder;
+use serde::Serialize;
+
+use crate::{
+    hash, layout, manifest::Manifest, missing::MissingList, svg, ticker, Error, Result, Symbol,
+};
+
+/// Something wrong with a logo file, or a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    /// The file is empty.
+    Empty,
+    /// The file isn't a (complete) SVG, e.g. as it was truncated.
+    Invalid,
+    /// The file doesn't match its manifest entry's size or digest.
+    Mismatch,
+    /// The file isn't the logo of any symbol in the symbol list.
+    Orphaned,
+    /// A symbol in the symbol list has no logo.
+    Missing,
+    /// A symbol in the symbol list has no logo, but none was found
+    /// upstream either (it's in `missing.txt`).
+    Unavailable,
+}
+
+impl Problem {
+    /// Whether the file is broken, and so can be deleted to have the
+    /// next run fetch it again.
+    pub fn is_corrupt(self) -> bool {
+        matches!(self, Self::Empty | Self::Invalid | Self::Mismatch)
+    }
+
+    /// Whether the output directory is in a bad state because of it;
+    /// a logo that isn't available upstream is only to be expected.
+    pub fn is_failure(self) -> bool {
+        self != Self::Unavailable
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Empty => "empty",
+            Self::Invalid => "not a valid SVG",
+            Self::Mismatch => "doesn't match the manifest",
+            Self::Orphaned => "not in the symbol list",
+            Self::Missing => "missing a logo",
+            Self::Unavailable => "missing a logo (not found upstream)",
+        })
+    }
+}
+
+/// A problem found by [`verify`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// The logo's path (relative to the output directory), or (for
+    /// [`Problem::Missing`]) the ticker without one.
+    pub name: String,
+    pub problem: Problem,
+}
+
+/// What [`verify`] found.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// How many logo files were checked.
+    pub checked: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    /// How many findings there are of each problem.
+    pub fn counts(&self) -> BTreeMap<Problem, usize> {
+        let mut counts = BTreeMap::new();
+        for finding in &self.findings {
+            *counts.entry(finding.problem).or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// Checks every logo (`.svg` or `.svgz` file) in `output`: that it
+/// isn't empty, that it's a well-formed SVG, and (given a `manifest`)
+/// that it matches its entry's size and digest. Given the `symbols`
+/// from the symbol list, also flags logos of symbols not in it, and
+/// symbols without a logo (as [unavailable](Problem::Unavailable) if
+/// they're in the `missing` list).
+pub async fn verify(
+    output: &Path,
+    symbols: Option<&[Symbol]>,
+    manifest: Option<&Manifest>,
+    missing: Option<&MissingList>,
+) -> Result<Report> {
+    let names = layout::list(output).await.map_err(|source| Error::Io {
+        path: output.to_path_buf(),
+        source,
+    })?;
+
+    let recorded = manifest
+        .map(|manifest| {
+            manifest
+                .logo
+                .iter()
+                .map(|entry| (entry.path.as_str(), entry))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .unwrap_or_default();
+    let stems = symbols.map(|symbols| {
+        symbols
+            .iter()
+            .filter_map(|symbol| ticker::file_stem(&symbol.ticker))
+            .collect::<BTreeSet<_>>()
+    });
+
+    let mut report = Report::default();
+    let mut found = BTreeSet::new();
+
+    for name in names {
+        let path = output.join(&name);
+        let content = tokio::fs::read(&path).await.map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        })?;
+        report.checked += 1;
+
+        // whatever casing the logo was written with
+        let stem = layout::stem(&name).to_uppercase();
+
+        let problem = if content.is_empty() {
+            Some(Problem::Empty)
+        } else if !is_valid_logo(&name, &content) {
+            Some(Problem::Invalid)
+        } else if recorded.get(name.as_str()).is_some_and(|entry| {
+            entry.bytes != content.len() || entry.sha256 != hash::sha256_hex(&content)
+        }) {
+            Some(Problem::Mismatch)
+        } else if stems.as_ref().is_some_and(|stems| !stems.contains(&stem)) {
+            Some(Problem::Orphaned)
+        } else {
+            None
+        };
+
+        if let Some(problem) = problem {
+            report.findings.push(Finding { name, problem });
+        }
+        found.insert(stem);
+    }
+
+    for symbol in symbols.unwrap_or_default() {
+        if ticker::file_stem(&symbol.ticker).is_some_and(|stem| !found.contains(&stem)) {
+            let unavailable = missing.is_some_and(|missing| missing.contains(&symbol.ticker));
+            report.findings.push(Finding {
+                name: symbol.ticker.clone(),
+                problem: if unavailable {
+                    Problem::Unavailable
+                } else {
+                    Problem::Missing
+                },
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Whether a logo file (gzipped, if it's an `.svgz`) is a
+/// well-formed SVG.
+fn is_valid_logo(name: &str, content: &[u8]) -> bool {
+    if !name.ends_with(".svgz") {
+        return svg::is_valid(content);
+    }
+
+    let mut svg = Vec::new();
+    GzDecoder::new(content).read_to_end(&mut svg).is_ok() && svg::is_valid(&svg)
+}
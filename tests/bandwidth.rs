@@ -0,0 +1,76 @@
+//! The bandwidth cap, timed against a local mock server. This is a
+//! test binary of its own so that nothing else competes with it.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{MockServer, Route, TempDir};
+use nyse_logos::{results::Outcome, LogoFetcher};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn bandwidth_is_capped_across_concurrent_fetches() {
+    const RATE: u64 = 1 << 20;
+    const SIZE: usize = 512 << 10;
+    const TICKERS: [&str; 4] = ["AAPL", "IBM", "MSFT", "XOM"];
+
+    let server = MockServer::start().await;
+    for ticker in TICKERS {
+        server.route(
+            &format!("/cdn/{}.svg", ticker.to_lowercase()),
+            Route::svg(common::large_logo(SIZE)).chunked(16 << 10, Duration::ZERO),
+        );
+    }
+
+    let dir = TempDir::new();
+    let fetcher = LogoFetcher::builder()
+        .output_dir(dir.path())
+        .url_template(server.template("/cdn"))
+        .jobs(TICKERS.len())
+        .max_bandwidth(RATE)
+        .sanitize(false)
+        .build()
+        .unwrap();
+    let symbols = TICKERS.map(common::symbol);
+
+    let started = Instant::now();
+    let summary = fetcher.fetch_all(&symbols).await.unwrap();
+    let elapsed = started.elapsed();
+
+    assert!(summary
+        .records
+        .iter()
+        .all(|record| record.outcome == Outcome::Fetched));
+
+    // the first tenth of a second's worth is let through at once
+    let total = summary
+        .records
+        .iter()
+        .map(|record| record.bytes.unwrap() as u64)
+        .sum::<u64>();
+    let expected = Duration::from_secs_f64((total - RATE / 10) as f64 / RATE as f64);
+    let error = (elapsed.as_secs_f64() - expected.as_secs_f64()).abs() / expected.as_secs_f64();
+    assert!(
+        error < 0.1,
+        "downloading {total} bytes took {elapsed:?} (expected about {expected:?})"
+    );
+}
+
+#[tokio::test]
+async fn zero_bandwidth_is_unlimited() {
+    let server = MockServer::start().await;
+    server.route("/cdn/aapl.svg", Route::svg(common::large_logo(2 << 20)));
+
+    let dir = TempDir::new();
+    let fetcher = LogoFetcher::builder()
+        .output_dir(dir.path())
+        .url_template(server.template("/cdn"))
+        .max_bandwidth(0)
+        .sanitize(false)
+        .build()
+        .unwrap();
+
+    assert!(fetcher.options().logo.bandwidth.is_none());
+    let record = fetcher.fetch_one(&common::symbol("AAPL")).await.unwrap();
+    assert_eq!(record.outcome, Outcome::Fetched);
+}
@@ -0,0 +1,336 @@
+//! A minimal HTTP/1.1 server for the integration tests to fetch
+//! symbol lists and logos from, and temporary output directories.
+
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// A plausible logo, larger than the default minimum size.
+pub const LOGO: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><rect width="10" height="10" fill="red"/></svg>"#;
+
+/// A valid SVG of (at least) `size` bytes, padded with a comment.
+pub fn large_logo(size: usize) -> Vec<u8> {
+    let (open, close) = LOGO.split_at(LOGO.len() - "</svg>".len());
+    let padding = size.saturating_sub(LOGO.len() + "<!---->".len());
+    format!("{open}<!--{}-->{close}", "x".repeat(padding)).into_bytes()
+}
+
+/// How the server responds to requests for a path.
+#[derive(Debug, Clone)]
+pub struct Route {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Bytes,
+    /// How long to wait before responding.
+    delay: Duration,
+    /// The size of the chunks the body is sent in, and how long to
+    /// wait before each one (after the first).
+    chunks: Option<(usize, Duration)>,
+    /// Whether to send a `Content-Length` header (if not, the body
+    /// ends when the connection is closed).
+    length: bool,
+}
+
+impl Route {
+    /// A `200 OK` with the given body.
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: Bytes::from(body.into()),
+            delay: Duration::ZERO,
+            chunks: None,
+            length: true,
+        }
+    }
+
+    /// A `200 OK` with the given body as an SVG.
+    pub fn svg(body: impl Into<Vec<u8>>) -> Self {
+        Self::ok(body).header("Content-Type", "image/svg+xml")
+    }
+
+    /// An empty response with the given status.
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            ..Self::ok(Vec::new())
+        }
+    }
+
+    /// A `302 Found` to `location`.
+    pub fn redirect(location: impl Into<String>) -> Self {
+        Self::status(302).header("Location", location)
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Waits this long before responding.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sends the body `size` bytes at a time, pausing before each
+    /// chunk after the first.
+    pub fn chunked(mut self, size: usize, pause: Duration) -> Self {
+        self.chunks = Some((size, pause));
+        self
+    }
+
+    /// Leaves out the `Content-Length` header.
+    pub fn without_length(mut self) -> Self {
+        self.length = false;
+        self
+    }
+}
+
+/// A request the server received.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct State {
+    routes: Mutex<HashMap<String, Route>>,
+    requests: Mutex<Vec<Request>>,
+    /// How many responses were sent whole, and how many were cut
+    /// off by the client going away.
+    completed: AtomicUsize,
+    aborted: AtomicUsize,
+}
+
+/// A server on a local port, answering `404` for paths without a
+/// [route](Route). It runs until the test's runtime shuts down.
+#[derive(Clone)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = Self {
+            addr: listener.local_addr().unwrap(),
+            state: Arc::default(),
+        };
+
+        let state = server.state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(respond(stream, state.clone()));
+            }
+        });
+
+        server
+    }
+
+    /// Responds to requests for `path` (e.g. `/aapl.svg`) as `route`
+    /// says.
+    pub fn route(&self, path: &str, route: Route) -> &Self {
+        self.state
+            .routes
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), route);
+        self
+    }
+
+    /// The URL of `path` on the server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// A logo URL template for the logos under `prefix` (e.g. `/cdn`).
+    pub fn template(&self, prefix: &str) -> String {
+        self.url(&format!("{prefix}/{{symbol}}.svg"))
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// How many requests there were for `path`.
+    pub fn hits(&self, path: &str) -> usize {
+        self.requests().iter().filter(|r| r.path == path).count()
+    }
+
+    pub fn completed(&self) -> usize {
+        self.state.completed.load(Ordering::SeqCst)
+    }
+
+    pub fn aborted(&self) -> usize {
+        self.state.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Waits (for up to 5s) until `path` has been requested.
+    pub async fn requested(&self, path: &str) {
+        eventually(|| self.hits(path) > 0).await;
+    }
+}
+
+/// Waits (for up to 5s) until `condition` holds, failing the test if
+/// it doesn't.
+pub async fn eventually(condition: impl Fn() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("condition never held");
+}
+
+/// A route for a logo whose body trickles in over a few seconds.
+pub fn slow_logo() -> Route {
+    Route::svg(large_logo(40 << 10)).chunked(1 << 10, Duration::from_millis(50))
+}
+
+async fn respond(mut stream: TcpStream, state: Arc<State>) {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default().to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let route = state.routes.lock().unwrap().get(&path).cloned();
+    state.requests.lock().unwrap().push(Request {
+        method: method.clone(),
+        path,
+        headers,
+    });
+    let route = route.unwrap_or_else(|| Route::status(404));
+
+    tokio::time::sleep(route.delay).await;
+
+    let mut response = format!("HTTP/1.1 {} Mock\r\nConnection: close\r\n", route.status);
+    if route.length {
+        response.push_str(&format!("Content-Length: {}\r\n", route.body.len()));
+    }
+    for (name, value) in &route.headers {
+        response.push_str(&format!("{name}: {value}\r\n"));
+    }
+    response.push_str("\r\n");
+
+    let sent = async {
+        stream.write_all(response.as_bytes()).await?;
+        if method != "HEAD" {
+            match route.chunks {
+                Some((size, pause)) => {
+                    for (i, chunk) in route.body.chunks(size.max(1)).enumerate() {
+                        if i > 0 {
+                            tokio::time::sleep(pause).await;
+                        }
+                        stream.write_all(chunk).await?;
+                        stream.flush().await?;
+                    }
+                }
+                None => stream.write_all(&route.body).await?,
+            }
+        }
+        stream.flush().await?;
+        stream.shutdown().await
+    }
+    .await;
+
+    let counter = match sent {
+        Ok(()) => &state.completed,
+        Err(_) => &state.aborted,
+    };
+    counter.fetch_add(1, Ordering::SeqCst);
+}
+
+/// A directory that's removed (with everything in it) when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "nyse-logos-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The names of the files in the directory (and not its
+    /// subdirectories), sorted.
+    pub fn files(&self) -> Vec<String> {
+        let mut files = std::fs::read_dir(&self.0)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_type().unwrap().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A symbol with just a ticker.
+pub fn symbol(ticker: &str) -> nyse_logos::Symbol {
+    nyse_logos::Symbol {
+        ticker: ticker.to_string(),
+        company_name: None,
+        issue_type: None,
+        exchange: None,
+        logo_file: None,
+        extra: Default::default(),
+    }
+}
+
+/// A logo config fetching from `template` only.
+pub fn logo_config(template: String) -> nyse_logos::LogoConfig {
+    nyse_logos::LogoConfig {
+        sources: vec![template],
+        ..Default::default()
+    }
+}
@@ -0,0 +1,287 @@
+//! Fetching batches of logos with a [`LogoFetcher`], from a local
+//! mock server.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{symbol, MockServer, Route, TempDir, LOGO};
+use futures_util::StreamExt;
+use nyse_logos::{exit::Status, results::Outcome, LogoFetcher};
+
+/// A fetcher writing to `dir`, fetching from the server's `/cdn`.
+fn fetcher(server: &MockServer, dir: &TempDir) -> LogoFetcher {
+    LogoFetcher::builder()
+        .output_dir(dir.path())
+        .url_template(server.template("/cdn"))
+        .retries(0)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn empty_and_tiny_bodies_are_too_small() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::svg(""))
+        .route("/cdn/msft.svg", Route::svg("<svg/>"))
+        .route("/cdn/ibm.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let summary = fetcher(&server, &dir)
+        .fetch_all(&["AAPL", "IBM", "MSFT"].map(symbol))
+        .await
+        .unwrap();
+
+    let outcomes = summary
+        .records
+        .iter()
+        .map(|record| (record.symbol.as_str(), record.outcome))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outcomes,
+        [
+            ("AAPL", Outcome::TooSmall),
+            ("IBM", Outcome::Fetched),
+            ("MSFT", Outcome::TooSmall)
+        ]
+    );
+    assert_eq!(dir.files(), ["IBM.svg"]);
+}
+
+#[tokio::test]
+async fn the_minimum_size_is_configurable() {
+    let server = MockServer::start().await;
+    server.route("/cdn/msft.svg", Route::svg("<svg/>"));
+
+    let dir = TempDir::new();
+    let fetcher = LogoFetcher::builder()
+        .output_dir(dir.path())
+        .url_template(server.template("/cdn"))
+        .min_size(1)
+        .build()
+        .unwrap();
+
+    let record = fetcher.fetch_one(&symbol("MSFT")).await.unwrap();
+    assert_eq!(record.outcome, Outcome::Fetched);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("MSFT.svg")).unwrap(),
+        "<svg/>"
+    );
+}
+
+#[tokio::test]
+async fn empty_logos_on_disk_are_fetched_again() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::svg(LOGO))
+        .route("/cdn/msft.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    std::fs::write(dir.path().join("AAPL.svg"), "").unwrap();
+    std::fs::write(dir.path().join("MSFT.svg"), "<svg>kept</svg>").unwrap();
+
+    let summary = fetcher(&server, &dir)
+        .fetch_all(&["AAPL", "MSFT"].map(symbol))
+        .await
+        .unwrap();
+
+    assert_eq!(summary.records[0].outcome, Outcome::Fetched);
+    assert_eq!(summary.records[1].outcome, Outcome::SkippedExisting);
+    assert_eq!(server.hits("/cdn/msft.svg"), 0);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("AAPL.svg")).unwrap(),
+        LOGO
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("MSFT.svg")).unwrap(),
+        "<svg>kept</svg>"
+    );
+}
+
+/// The exit status after fetching AAPL, IBM and MSFT, with AAPL's
+/// logo responding as `route` says.
+async fn status_with(route: Route, fail_on_missing: bool, threshold: usize) -> Status {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", route)
+        .route("/cdn/ibm.svg", Route::svg(LOGO))
+        .route("/cdn/msft.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    fetcher(&server, &dir)
+        .fetch_all(&["AAPL", "IBM", "MSFT"].map(symbol))
+        .await
+        .unwrap()
+        .status(fail_on_missing, threshold)
+}
+
+#[tokio::test]
+async fn fetching_everything_exits_with_0() {
+    let status = status_with(Route::svg(LOGO), true, 0).await;
+    assert_eq!(status, Status::Success);
+    assert_eq!(status.code(), 0);
+}
+
+#[tokio::test]
+async fn missing_logos_exit_with_2_only_when_failing_on_missing() {
+    assert_eq!(status_with(Route::status(404), false, 0).await.code(), 0);
+    assert_eq!(status_with(Route::status(404), true, 0).await.code(), 2);
+}
+
+#[tokio::test]
+async fn failed_fetches_exit_with_2_over_the_threshold() {
+    let status = status_with(Route::status(500), false, 0).await;
+    assert_eq!(status, Status::FetchFailed);
+    assert_eq!(status.code(), 2);
+
+    assert_eq!(status_with(Route::status(500), false, 1).await.code(), 0);
+    assert_eq!(status_with(Route::svg("<html/>"), false, 0).await.code(), 2);
+}
+
+#[tokio::test]
+async fn giving_up_exits_with_2() {
+    let server = MockServer::start().await;
+    for ticker in ["aapl", "ibm", "msft", "xom"] {
+        server.route(&format!("/cdn/{ticker}.svg"), Route::status(503));
+    }
+
+    let dir = TempDir::new();
+    let fetcher = LogoFetcher::builder()
+        .output_dir(dir.path())
+        .url_template(server.template("/cdn"))
+        .jobs(1)
+        .retries(0)
+        .circuit_breaker(2, Duration::from_millis(10), 1)
+        .build()
+        .unwrap();
+    let summary = fetcher
+        .fetch_all(&["AAPL", "IBM", "MSFT", "XOM"].map(symbol))
+        .await
+        .unwrap();
+
+    assert!(summary.gave_up);
+    assert!(summary
+        .records
+        .iter()
+        .any(|record| record.outcome == Outcome::NotAttempted));
+    assert_eq!(summary.status(false, 100).code(), 2);
+}
+
+#[tokio::test]
+async fn streamed_results_arrive_as_they_finish() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", common::slow_logo())
+        .route("/cdn/ibm.svg", Route::svg(LOGO))
+        .route("/cdn/msft.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let started = Instant::now();
+    let mut stream = fetcher(&server, &dir).fetch_stream(&["AAPL", "IBM", "MSFT"].map(symbol));
+
+    let mut first = vec![
+        stream.next().await.unwrap().symbol,
+        stream.next().await.unwrap().symbol,
+    ];
+    first.sort();
+    assert_eq!(first, ["IBM", "MSFT"]);
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(server.completed(), 2);
+
+    assert_eq!(stream.next().await.unwrap().symbol, "AAPL");
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn dropping_the_stream_cancels_in_flight_fetches() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", common::slow_logo())
+        .route("/cdn/msft.svg", common::slow_logo());
+
+    let dir = TempDir::new();
+    let fetcher = fetcher(&server, &dir);
+    let stream = fetcher.fetch_stream(&["AAPL", "MSFT"].map(symbol));
+
+    server.requested("/cdn/aapl.svg").await;
+    server.requested("/cdn/msft.svg").await;
+    drop(stream);
+
+    common::eventually(|| server.aborted() == 2).await;
+    assert_eq!(server.completed(), 0);
+    assert!(dir.files().iter().all(|file| !file.ends_with(".svg")));
+}
+
+#[tokio::test]
+async fn a_fetcher_can_be_reused_after_a_cancelled_batch() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::status(503))
+        .route("/cdn/ibm.svg", common::slow_logo())
+        .route("/cdn/msft.svg", Route::svg(LOGO))
+        .route("/cdn/xom.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let fetcher = LogoFetcher::builder()
+        .output_dir(dir.path())
+        .url_template(server.template("/cdn"))
+        .retries(0)
+        .circuit_breaker(1, Duration::from_millis(10), 3)
+        .build()
+        .unwrap();
+    let summary = fetcher.fetch_all(&[symbol("AAPL")]).await.unwrap();
+    assert!(!summary.gave_up);
+
+    // cancelled while its fetch is the breaker's probe
+    let probe = [symbol("IBM")];
+    tokio::select! {
+        _ = fetcher.fetch_all(&probe) => panic!("the slow fetch finished"),
+        () = server.requested("/cdn/ibm.svg") => {}
+    }
+
+    let summary = tokio::time::timeout(
+        Duration::from_secs(5),
+        fetcher.fetch_all(&["MSFT", "XOM"].map(symbol)),
+    )
+    .await
+    .expect("the fetcher stalled")
+    .unwrap();
+    assert!(summary
+        .records
+        .iter()
+        .all(|record| record.outcome == Outcome::Fetched));
+    assert!(dir.path().join("MSFT.svg").exists());
+    assert!(dir.path().join("XOM.svg").exists());
+}
+
+#[tokio::test]
+async fn dropping_the_stream_mid_probe_doesnt_stall_the_fetcher() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::status(503))
+        .route("/cdn/ibm.svg", common::slow_logo())
+        .route("/cdn/msft.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let fetcher = LogoFetcher::builder()
+        .output_dir(dir.path())
+        .url_template(server.template("/cdn"))
+        .retries(0)
+        .circuit_breaker(1, Duration::from_millis(10), 3)
+        .build()
+        .unwrap();
+    fetcher.fetch_one(&symbol("AAPL")).await.unwrap();
+
+    // the first fetch after the cool-down is the breaker's probe
+    let stream = fetcher.fetch_stream(&[symbol("IBM")]);
+    server.requested("/cdn/ibm.svg").await;
+    drop(stream);
+
+    let record = tokio::time::timeout(Duration::from_secs(5), fetcher.fetch_one(&symbol("MSFT")))
+        .await
+        .expect("the fetcher stalled")
+        .unwrap();
+    assert_eq!(record.outcome, Outcome::Fetched);
+    assert!(!fetcher.gave_up());
+}
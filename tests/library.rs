@@ -0,0 +1,262 @@
+//! The library API, used the way another tool would use it.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{logo_config, symbol, MockServer, Route, TempDir, LOGO};
+use futures_util::StreamExt;
+use nyse_logos::{
+    fetch_logo, fetch_logo_stream, fetch_symbols, Error, FetchOptions, LogoOutcome, SymbolSource,
+    Tsv,
+};
+
+const SYMBOLS: &str = "Symbol\tCompany\tRound Lot\n\
+                       AAPL\tApple Inc.\t100\n\
+                       BRK A\tBerkshire Hathaway\t1\n\
+                       \tNo ticker\t100\n";
+
+#[test]
+fn tsv_exposes_headers_rows_and_columns() {
+    let tsv = Tsv::from_delimited(SYMBOLS, '\t').unwrap();
+
+    assert_eq!(tsv.headers(), ["Symbol", "Company", "Round Lot"]);
+    // The row without a symbol is dropped.
+    assert_eq!(tsv.rows().len(), 2);
+    assert_eq!(
+        tsv.column("Company").unwrap().collect::<Vec<_>>(),
+        ["Apple Inc.", "Berkshire Hathaway"]
+    );
+    assert!(tsv.column("Exchange").is_none());
+
+    let row = &tsv.rows()[1];
+    assert_eq!(tsv.get(row, "Symbol"), Some("BRK A"));
+    assert_eq!(tsv.get(row, "Round Lot"), Some("1"));
+    assert_eq!(tsv.get(row, "Exchange"), None);
+}
+
+#[tokio::test]
+async fn fetch_symbols_maps_rows_to_symbols() {
+    let server = MockServer::start().await;
+    server.route("/symbols.txt", Route::ok(SYMBOLS));
+
+    let client = reqwest::Client::new();
+    let symbols = fetch_symbols(&client, SymbolSource::Url(server.url("/symbols.txt")))
+        .await
+        .unwrap();
+
+    let tickers = symbols
+        .iter()
+        .map(|s| s.ticker.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tickers, ["AAPL", "BRK A"]);
+    assert_eq!(symbols[0].company_name.as_deref(), Some("Apple Inc."));
+    assert_eq!(symbols[0].extra["round_lot"], "100");
+    assert!(symbols[1].logo_file.is_some());
+}
+
+#[tokio::test]
+async fn fetch_symbols_sniffs_lists_arriving_in_tiny_chunks() {
+    let server = MockServer::start().await;
+    server.route(
+        "/symbols.txt",
+        Route::ok(SYMBOLS).chunked(3, Duration::from_millis(1)),
+    );
+
+    let client = reqwest::Client::new();
+    let symbols = fetch_symbols(&client, SymbolSource::Url(server.url("/symbols.txt")))
+        .await
+        .unwrap();
+
+    let tickers = symbols
+        .iter()
+        .map(|s| s.ticker.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tickers, ["AAPL", "BRK A"]);
+}
+
+#[tokio::test]
+async fn fetch_symbols_still_rejects_html_arriving_in_tiny_chunks() {
+    let server = MockServer::start().await;
+    server.route(
+        "/symbols.txt",
+        Route::ok("<!DOCTYPE html>\n<html><body>Not Found</body></html>\n")
+            .chunked(3, Duration::from_millis(1)),
+    );
+
+    let client = reqwest::Client::new();
+    let err = fetch_symbols(&client, SymbolSource::Url(server.url("/symbols.txt")))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::UnexpectedContent(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn fetch_symbols_reports_http_errors() {
+    let server = MockServer::start().await;
+
+    let client = reqwest::Client::new();
+    let err = fetch_symbols(&client, SymbolSource::Url(server.url("/missing.txt")))
+        .await
+        .unwrap_err();
+
+    match err {
+        Error::SymbolListHttp { status, .. } => assert_eq!(status.as_u16(), 404),
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+#[tokio::test]
+async fn fetch_symbols_reads_files() {
+    let dir = TempDir::new();
+    let path = dir.path().join("symbols.txt");
+    std::fs::write(&path, SYMBOLS).unwrap();
+
+    let client = reqwest::Client::new();
+    let symbols = fetch_symbols(&client, SymbolSource::File(path))
+        .await
+        .unwrap();
+
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(
+        symbols[1].company_name.as_deref(),
+        Some("Berkshire Hathaway")
+    );
+}
+
+#[tokio::test]
+async fn fetch_logo_writes_the_logo() {
+    let server = MockServer::start().await;
+    server.route("/cdn/aapl.svg", Route::svg(LOGO).header("ETag", "\"v1\""));
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let config = logo_config(server.template("/cdn"));
+    let outcome = fetch_logo(&client, "AAPL", dir.path(), &config, None)
+        .await
+        .unwrap();
+
+    match outcome {
+        LogoOutcome::Fetched {
+            url,
+            path,
+            bytes,
+            status,
+            etag,
+            ..
+        } => {
+            assert_eq!(url, server.url("/cdn/aapl.svg"));
+            assert_eq!(status.as_u16(), 200);
+            assert_eq!(etag.as_deref(), Some("\"v1\""));
+            assert_eq!(bytes, LOGO.len());
+            assert_eq!(std::fs::read_to_string(path).unwrap(), LOGO);
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert_eq!(dir.files(), ["AAPL.svg"]);
+}
+
+#[tokio::test]
+async fn fetch_logo_reports_missing_logos() {
+    let server = MockServer::start().await;
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let config = logo_config(server.template("/cdn"));
+    let outcome = fetch_logo(&client, "ZZZZ", dir.path(), &config, None)
+        .await
+        .unwrap();
+
+    match outcome {
+        LogoOutcome::HttpError { attempts, .. } => {
+            assert!(!attempts.is_empty());
+            assert!(attempts.iter().all(|(_, status)| status.as_u16() == 404));
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert!(dir.files().is_empty());
+}
+
+#[tokio::test]
+async fn fetch_symbols_decodes_the_response_charset() {
+    let server = MockServer::start().await;
+    let content = include_bytes!("fixtures/windows_1252.tsv");
+    server
+        .route(
+            "/sniffed.txt",
+            Route::ok(content.as_slice()).header("Content-Type", "text/plain"),
+        )
+        .route(
+            "/labelled.txt",
+            Route::ok(content.as_slice())
+                .header("Content-Type", "text/plain; charset=windows-1252"),
+        );
+
+    let client = reqwest::Client::new();
+    for path in ["/sniffed.txt", "/labelled.txt"] {
+        let symbols = fetch_symbols(&client, SymbolSource::Url(server.url(path)))
+            .await
+            .unwrap();
+
+        let names = symbols
+            .iter()
+            .map(|s| s.company_name.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Apple Inc.", "Nestlé S.A.", "Section § Holdings"]);
+    }
+}
+
+/// Options for fetching from the server's `/cdn` into `dir`.
+fn fetch_options(server: &MockServer, dir: &TempDir) -> FetchOptions {
+    FetchOptions {
+        output: dir.path().to_path_buf(),
+        logo: logo_config(server.template("/cdn")),
+        ..FetchOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn fetch_logo_stream_yields_results_as_they_finish() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", common::slow_logo())
+        .route("/cdn/msft.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let started = Instant::now();
+    let mut stream = fetch_logo_stream(
+        &client,
+        &["AAPL", "MSFT"].map(symbol),
+        &fetch_options(&server, &dir),
+    );
+
+    let first = stream.next().await.unwrap();
+    assert_eq!(first.symbol, "MSFT");
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(server.completed(), 1);
+    assert!(dir.path().join("MSFT.svg").exists());
+    assert!(!dir.path().join("AAPL.svg").exists());
+
+    let second = stream.next().await.unwrap();
+    assert_eq!(second.symbol, "AAPL");
+    assert!(stream.next().await.is_none());
+    assert_eq!(dir.files(), ["AAPL.svg", "MSFT.svg"]);
+}
+
+#[tokio::test]
+async fn dropping_the_logo_stream_cancels_its_fetches() {
+    let server = MockServer::start().await;
+    server.route("/cdn/aapl.svg", common::slow_logo());
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let stream = fetch_logo_stream(&client, &[symbol("AAPL")], &fetch_options(&server, &dir));
+
+    server.requested("/cdn/aapl.svg").await;
+    drop(stream);
+
+    common::eventually(|| server.aborted() == 1).await;
+    assert_eq!(server.completed(), 0);
+    assert!(!dir.path().join("AAPL.svg").exists());
+}
@@ -0,0 +1,415 @@
+//! Fetching single logos from a local mock server.
+
+mod common;
+
+use common::{logo_config, MockServer, Route, TempDir, LOGO};
+use std::time::Duration;
+
+use nyse_logos::{
+    download_logo, fetch_logo, hash, redirect_policy, ContentTypeCheck, Error, LogoConfig,
+    LogoOutcome,
+};
+
+/// Fetches `symbol`'s logo into `dir`, returning the written path.
+async fn fetch_to(dir: &TempDir, symbol: &str, config: &LogoConfig) -> std::path::PathBuf {
+    let client = reqwest::Client::new();
+    match fetch_logo(&client, symbol, dir.path(), config, None).await {
+        Ok(LogoOutcome::Fetched { path, .. }) => path,
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+}
+
+const HTML: &str = "<!DOCTYPE html><html><body><h1>Not Found</h1><p>Sorry.</p></body></html>";
+
+const LATIN1: &[u8] = include_bytes!("fixtures/latin1.svg");
+
+#[tokio::test]
+async fn non_utf8_logos_are_written_byte_for_byte() {
+    let server = MockServer::start().await;
+    server.route("/cdn/nsrgy.svg", Route::svg(LATIN1));
+
+    let dir = TempDir::new();
+    let path = fetch_to(&dir, "NSRGY", &logo_config(server.template("/cdn"))).await;
+
+    assert_eq!(std::fs::read(path).unwrap(), LATIN1);
+}
+
+#[tokio::test]
+async fn binary_bodies_are_written_byte_for_byte() {
+    // a gzip header, which isn't valid UTF-8
+    let body = (0..=255).cycle().take(4096).collect::<Vec<u8>>();
+    let gzip = [&[0x1f, 0x8b, 0x08, 0x00][..], &body].concat();
+
+    let server = MockServer::start().await;
+    server.route(
+        "/cdn/aapl.svg",
+        Route::ok(gzip.clone()).header("Content-Type", "application/octet-stream"),
+    );
+
+    let dir = TempDir::new();
+    let config = LogoConfig {
+        validate: false,
+        sanitize: false,
+        content_type: ContentTypeCheck::Ignore,
+        ..logo_config(server.template("/cdn"))
+    };
+    let path = fetch_to(&dir, "AAPL", &config).await;
+
+    assert_eq!(std::fs::read(path).unwrap(), gzip);
+}
+
+#[tokio::test]
+async fn large_logos_are_streamed_to_disk() {
+    let logo = common::large_logo(3 << 20);
+
+    let server = MockServer::start().await;
+    server.route(
+        "/cdn/aapl.svg",
+        Route::svg(logo.clone()).chunked(64 << 10, Duration::ZERO),
+    );
+
+    let dir = TempDir::new();
+    let config = LogoConfig {
+        sanitize: false,
+        ..logo_config(server.template("/cdn"))
+    };
+    let path = fetch_to(&dir, "AAPL", &config).await;
+
+    assert_eq!(std::fs::read(path).unwrap(), logo);
+    assert_eq!(dir.files(), ["AAPL.svg"]);
+}
+
+#[tokio::test]
+async fn logos_over_the_size_cap_leave_no_partial_file() {
+    let logo = common::large_logo(1 << 20);
+
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::svg(logo.clone()))
+        .route(
+            "/cdn/msft.svg",
+            Route::svg(logo)
+                .chunked(16 << 10, Duration::from_millis(1))
+                .without_length(),
+        );
+
+    let dir = TempDir::new();
+    let config = LogoConfig {
+        max_size: 256 << 10,
+        sanitize: false,
+        ..logo_config(server.template("/cdn"))
+    };
+
+    let client = reqwest::Client::new();
+    for symbol in ["AAPL", "MSFT"] {
+        match fetch_logo(&client, symbol, dir.path(), &config, None).await {
+            Err(Error::TooLarge { limit, .. }) => assert_eq!(limit, 256 << 10),
+            outcome => panic!("unexpected outcome: {outcome:?}"),
+        }
+    }
+    assert!(dir.files().is_empty());
+}
+
+/// A config trying the `/primary` source, then the `/secondary` one.
+fn two_sources(server: &MockServer) -> LogoConfig {
+    LogoConfig {
+        sources: vec![server.template("/primary"), server.template("/secondary")],
+        ..Default::default()
+    }
+}
+
+/// Fetches AAPL's logo with the `primary` route failing, asserting
+/// that it came from the secondary source instead.
+async fn assert_falls_back(primary: Route) {
+    let server = MockServer::start().await;
+    server
+        .route("/primary/aapl.svg", primary)
+        .route("/secondary/aapl.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let config = two_sources(&server);
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Ok(LogoOutcome::Fetched { source, path, .. }) => {
+            assert_eq!(source, server.template("/secondary"));
+            assert_eq!(std::fs::read_to_string(path).unwrap(), LOGO);
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert_eq!(server.hits("/primary/aapl.svg"), 1);
+    assert_eq!(dir.files(), ["AAPL.svg"]);
+}
+
+#[tokio::test]
+async fn missing_logos_fall_back_to_the_next_source() {
+    assert_falls_back(Route::status(404)).await;
+}
+
+#[tokio::test]
+async fn server_errors_fall_back_to_the_next_source() {
+    assert_falls_back(Route::status(500)).await;
+}
+
+#[tokio::test]
+async fn html_bodies_fall_back_to_the_next_source() {
+    assert_falls_back(Route::svg(HTML)).await;
+}
+
+#[tokio::test]
+async fn json_bodies_fall_back_to_the_next_source() {
+    let json = r#"{"error": "logo not found", "symbol": "AAPL", "status": 404}"#;
+    assert_falls_back(Route::svg(json)).await;
+}
+
+#[tokio::test]
+async fn tiny_bodies_fall_back_to_the_next_source() {
+    assert_falls_back(Route::svg("<svg/>")).await;
+}
+
+#[tokio::test]
+async fn unparseable_bodies_fall_back_to_the_next_source() {
+    let broken = format!("{}<g><rect></svg>", &LOGO[..LOGO.len() - "</svg>".len()]);
+    assert_falls_back(Route::svg(broken)).await;
+}
+
+#[tokio::test]
+async fn unreachable_sources_fall_back_to_the_next_source() {
+    let closed = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/{{symbol}}.svg", listener.local_addr().unwrap())
+    };
+
+    let server = MockServer::start().await;
+    server.route("/secondary/aapl.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let config = LogoConfig {
+        sources: vec![closed, server.template("/secondary")],
+        ..Default::default()
+    };
+    fetch_to(&dir, "AAPL", &config).await;
+}
+
+#[tokio::test]
+async fn downloads_fall_back_to_the_next_source() {
+    let server = MockServer::start().await;
+    server
+        .route("/primary/aapl.svg", Route::svg("{\"error\":\"not found\"}"))
+        .route("/secondary/aapl.svg", Route::svg(LOGO));
+
+    let client = reqwest::Client::new();
+    match download_logo(&client, "AAPL", &two_sources(&server)).await {
+        Ok(LogoOutcome::Downloaded(logo)) => {
+            assert_eq!(logo.source, server.template("/secondary"));
+            assert_eq!(logo.content, LOGO.as_bytes());
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+}
+
+#[tokio::test]
+async fn the_last_unusable_body_is_reported_if_no_source_has_a_logo() {
+    let server = MockServer::start().await;
+    server
+        .route("/primary/aapl.svg", Route::svg("<svg/>"))
+        .route("/secondary/aapl.svg", Route::svg(HTML));
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let config = two_sources(&server);
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Err(Error::InvalidContent { url }) => assert_eq!(url, server.url("/secondary/aapl.svg")),
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert!(dir.files().is_empty());
+}
+
+#[tokio::test]
+async fn every_source_missing_the_logo_is_an_http_error() {
+    let server = MockServer::start().await;
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let config = two_sources(&server);
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Ok(LogoOutcome::HttpError { attempts, .. }) => {
+            let urls = attempts.into_iter().map(|(url, _)| url).collect::<Vec<_>>();
+            assert_eq!(
+                urls,
+                [
+                    server.url("/primary/aapl.svg"),
+                    server.url("/secondary/aapl.svg")
+                ]
+            );
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+}
+
+/// A client following redirects as the CLI does.
+fn redirecting_client(max: usize, same_host_only: bool) -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(redirect_policy(max, same_host_only))
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn redirected_logos_are_reported_with_their_final_url() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::redirect("/moved/aapl.svg"))
+        .route(
+            "/moved/aapl.svg",
+            Route::redirect(server.url("/final/aapl.svg")),
+        )
+        .route("/final/aapl.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let client = redirecting_client(5, false);
+    let config = logo_config(server.template("/cdn"));
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Ok(LogoOutcome::Fetched { url, final_url, .. }) => {
+            assert_eq!(url, server.url("/cdn/aapl.svg"));
+            assert_eq!(final_url, Some(server.url("/final/aapl.svg")));
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+}
+
+#[tokio::test]
+async fn redirects_are_not_followed_when_disabled() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::redirect("/placeholder.svg"))
+        .route("/placeholder.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let client = redirecting_client(0, false);
+    let config = logo_config(server.template("/cdn"));
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Ok(LogoOutcome::HttpError { attempts, .. }) => {
+            assert_eq!(attempts[0].1.as_u16(), 302);
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert_eq!(server.hits("/placeholder.svg"), 0);
+    assert!(dir.files().is_empty());
+}
+
+#[tokio::test]
+async fn too_many_redirects_fail() {
+    let server = MockServer::start().await;
+    server
+        .route("/cdn/aapl.svg", Route::redirect("/a.svg"))
+        .route("/a.svg", Route::redirect("/b.svg"))
+        .route("/b.svg", Route::redirect("/c.svg"))
+        .route("/c.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let client = redirecting_client(2, false);
+    let config = logo_config(server.template("/cdn"));
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Err(Error::Http { url, .. }) => assert_eq!(url, server.url("/cdn/aapl.svg")),
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert_eq!(server.hits("/c.svg"), 0);
+}
+
+#[tokio::test]
+async fn cross_host_redirects_can_be_refused() {
+    let server = MockServer::start().await;
+    // the same server, under another host name
+    let elsewhere = server
+        .url("/placeholder.svg")
+        .replace("127.0.0.1", "localhost");
+    server
+        .route("/cdn/aapl.svg", Route::redirect(elsewhere))
+        .route("/cdn/msft.svg", Route::redirect("/moved/msft.svg"))
+        .route("/moved/msft.svg", Route::svg(LOGO))
+        .route("/placeholder.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let client = redirecting_client(5, true);
+    let config = logo_config(server.template("/cdn"));
+
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Ok(LogoOutcome::HttpError { attempts, .. }) => {
+            assert_eq!(attempts[0].1.as_u16(), 302);
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert_eq!(server.hits("/placeholder.svg"), 0);
+
+    // redirects within the host are still followed
+    match fetch_logo(&client, "MSFT", dir.path(), &config, None).await {
+        Ok(LogoOutcome::Fetched { final_url, .. }) => {
+            assert_eq!(final_url, Some(server.url("/moved/msft.svg")));
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert_eq!(dir.files(), ["MSFT.svg"]);
+
+    // and cross-host ones without the restriction
+    let client = redirecting_client(5, false);
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Ok(LogoOutcome::Fetched { final_url, .. }) => {
+            assert!(final_url.unwrap().starts_with("http://localhost:"));
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+}
+
+/// A generic "no logo" image, as some sources serve for every
+/// unknown ticker.
+const PLACEHOLDER: &str =
+    r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><text>?</text></svg>"#;
+
+fn with_placeholder(config: LogoConfig) -> LogoConfig {
+    LogoConfig {
+        placeholders: [hash::sha256_hex(PLACEHOLDER.as_bytes())].into(),
+        ..config
+    }
+}
+
+#[tokio::test]
+async fn placeholders_fall_back_to_the_next_source() {
+    let server = MockServer::start().await;
+    server
+        .route("/primary/aapl.svg", Route::svg(PLACEHOLDER))
+        .route("/secondary/aapl.svg", Route::svg(LOGO));
+
+    let dir = TempDir::new();
+    let config = with_placeholder(two_sources(&server));
+    let path = fetch_to(&dir, "AAPL", &config).await;
+
+    assert_eq!(std::fs::read_to_string(path).unwrap(), LOGO);
+    assert_eq!(dir.files(), ["AAPL.svg"]);
+
+    let client = reqwest::Client::new();
+    match download_logo(&client, "AAPL", &config).await {
+        Ok(LogoOutcome::Downloaded(logo)) => assert_eq!(logo.content, LOGO.as_bytes()),
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+}
+
+#[tokio::test]
+async fn placeholders_from_every_source_are_reported() {
+    let server = MockServer::start().await;
+    server
+        .route("/primary/aapl.svg", Route::svg(PLACEHOLDER))
+        .route("/secondary/aapl.svg", Route::svg(PLACEHOLDER));
+
+    let dir = TempDir::new();
+    let client = reqwest::Client::new();
+    let config = with_placeholder(two_sources(&server));
+    match fetch_logo(&client, "AAPL", dir.path(), &config, None).await {
+        Ok(LogoOutcome::Placeholder { url, sha256 }) => {
+            assert_eq!(url, server.url("/secondary/aapl.svg"));
+            assert_eq!(sha256, hash::sha256_hex(PLACEHOLDER.as_bytes()));
+        }
+        outcome => panic!("unexpected outcome: {outcome:?}"),
+    }
+    assert_eq!(server.hits("/secondary/aapl.svg"), 1);
+    assert!(dir.files().is_empty());
+}
@@ -0,0 +1,179 @@
+//! Memory use while downloading large logos and symbol lists. This is a test binary
+//! of its own (and its tests take turns) so that nothing else
+//! allocates while it's measured.
+
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use common::{logo_config, MockServer, Route, TempDir};
+use nyse_logos::{
+    fetch_logo, for_each_symbol, Error, LogoConfig, LogoOutcome, SymbolSource,
+    MAX_PROCESSED_LOGO_SIZE,
+};
+
+/// Tracks the most memory allocated at once.
+struct PeakAlloc;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+static TURN: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Runs `fetch`, returning its result and the most memory that was
+/// allocated at once (beyond what already was) while it ran.
+async fn measure<T>(fetch: impl std::future::Future<Output = T>) -> (T, usize) {
+    let before = LIVE.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let result = fetch.await;
+    (result, PEAK.load(Ordering::SeqCst) - before)
+}
+
+#[tokio::test]
+async fn streaming_memory_is_bounded_by_the_chunk_size() {
+    const SIZE: usize = 16 << 20;
+    let _turn = TURN.lock().await;
+
+    let server = MockServer::start().await;
+    // the mock server holds the body in memory, but from before the
+    // measuring starts
+    server.route(
+        "/cdn/aapl.svg",
+        Route::svg(common::large_logo(SIZE)).chunked(64 << 10, Duration::ZERO),
+    );
+
+    let dir = TempDir::new();
+    let config = LogoConfig {
+        max_size: 2 * SIZE as u64,
+        sanitize: false,
+        ..logo_config(server.template("/cdn"))
+    };
+    let client = reqwest::Client::new();
+
+    let (outcome, peak) = measure(fetch_logo(&client, "AAPL", dir.path(), &config, None)).await;
+    assert!(matches!(outcome, Ok(LogoOutcome::Fetched { bytes, .. }) if bytes >= SIZE));
+    assert!(
+        peak < 2 << 20,
+        "downloading {SIZE} bytes peaked at {peak} bytes allocated"
+    );
+}
+
+#[tokio::test]
+async fn sanitized_logos_are_buffered_up_to_the_processing_cap() {
+    const SIZE: usize = 16 << 20;
+    let _turn = TURN.lock().await;
+
+    let server = MockServer::start().await;
+    server.route(
+        "/cdn/aapl.svg",
+        Route::svg(common::large_logo(SIZE))
+            .chunked(64 << 10, Duration::ZERO)
+            .without_length(),
+    );
+
+    let dir = TempDir::new();
+    let config = LogoConfig {
+        max_size: 2 * SIZE as u64,
+        ..logo_config(server.template("/cdn"))
+    };
+    assert!(config.sanitize);
+    let client = reqwest::Client::new();
+
+    let (outcome, peak) = measure(fetch_logo(&client, "AAPL", dir.path(), &config, None)).await;
+    assert!(
+        matches!(
+            outcome,
+            Err(Error::TooLarge {
+                limit: MAX_PROCESSED_LOGO_SIZE,
+                ..
+            })
+        ),
+        "{outcome:?}"
+    );
+    assert!(dir.files().is_empty());
+
+    // growing the buffer (to at most the cap) briefly holds both its
+    // old and new allocations
+    assert!(
+        peak < 3 * MAX_PROCESSED_LOGO_SIZE as usize,
+        "sanitizing peaked at {peak} bytes allocated"
+    );
+}
+
+#[tokio::test]
+async fn logos_under_the_processing_cap_are_sanitized() {
+    let _turn = TURN.lock().await;
+
+    let server = MockServer::start().await;
+    let logo = common::large_logo(MAX_PROCESSED_LOGO_SIZE as usize / 2);
+    server.route(
+        "/cdn/aapl.svg",
+        Route::svg(logo.clone()).chunked(64 << 10, Duration::ZERO),
+    );
+
+    let dir = TempDir::new();
+    let config = logo_config(server.template("/cdn"));
+    let client = reqwest::Client::new();
+
+    let outcome = fetch_logo(&client, "AAPL", dir.path(), &config, None).await;
+    assert!(
+        matches!(outcome, Ok(LogoOutcome::Fetched { bytes, .. }) if bytes == logo.len()),
+        "{outcome:?}"
+    );
+}
+
+#[tokio::test]
+async fn symbol_list_memory_does_not_grow_with_its_size() {
+    const ROWS: usize = 200_000;
+    let _turn = TURN.lock().await;
+
+    let mut list = String::from("Symbol\tCompany\tRound Lot\n");
+    for i in 0..ROWS {
+        list.push_str(&format!("S{i}\tCompany number {i}, Inc.\t100\n"));
+    }
+    let size = list.len();
+
+    let server = MockServer::start().await;
+    server.route(
+        "/symbols.txt",
+        Route::ok(list)
+            .chunked(64 << 10, Duration::ZERO)
+            .without_length(),
+    );
+    let client = reqwest::Client::new();
+
+    let mut count = 0;
+    let (result, peak) = measure(for_each_symbol(
+        &client,
+        SymbolSource::Url(server.url("/symbols.txt")),
+        |_| count += 1,
+    ))
+    .await;
+    result.unwrap();
+
+    assert_eq!(count, ROWS);
+    assert!(
+        peak < 2 << 20,
+        "parsing a {size} byte symbol list peaked at {peak} bytes allocated"
+    );
+}